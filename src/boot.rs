@@ -0,0 +1,222 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+static RE_JOURNAL_BOOT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^-- (?:Boot ([0-9a-fA-F]+)|Reboot) --").unwrap());
+static RE_KERNEL_BANNER: Lazy<Regex> = Lazy::new(|| Regex::new(r"Linux version \d").unwrap());
+// "journalctl -b -1 -k" or "journalctl --boot=-1": the section's last boot is that offset
+static RE_BOOT_ARG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:-b|--boot)[ =]?(-\d+)\b").unwrap());
+static RE_DMESG_TS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\[\s*(\d+\.\d+)\]").unwrap());
+
+/// One boot of a journal or dmesg section, as a byte range of the log.
+/// `offset` follows journalctl -b: 0 is the current boot, -1 the one before. The last
+/// boot of a section is the current one unless the section title asks for another (-b -1).
+#[derive(Debug, Clone)]
+pub struct Boot {
+    pub offset: i32,
+    pub id: Option<String>,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BootMap {
+    pub boots: Vec<Boot>,
+    /// Start of every "____" section separator.
+    sections: Vec<usize>,
+}
+
+/// Findings of one message per report section.
+type Sections<T> = Vec<(usize, Vec<(usize, T)>)>;
+
+impl BootMap {
+    /// Splits every report section ("____" separated) at "-- Boot <id> --" markers,
+    /// "Linux version" kernel banners and dmesg timestamps going backwards.
+    pub fn parse(log: &str) -> BootMap {
+        let mut boots = Vec::new();
+        let mut sections = Vec::new();
+        let mut section: Vec<Boot> = Vec::new();
+        let mut section_start = 0;
+        let mut title: Option<&str> = None;
+        // Set right after a boundary so a banner directly following "-- Boot" isn't a second boot
+        let mut fresh = false;
+        let mut last_ts = 0.0;
+        let mut pos = 0;
+
+        for line in log.split_inclusive('\n') {
+            let start = pos;
+            pos += line.len();
+
+            if line.starts_with("____") {
+                sections.push(start);
+                close_section(&mut boots, &mut section, title, section_start, start);
+                section_start = pos;
+                title = None;
+                last_ts = 0.0;
+                fresh = false;
+                continue;
+            }
+
+            if title.is_none() && !line.trim().is_empty() {
+                title = Some(line.trim());
+            }
+            let ts = RE_DMESG_TS.captures(line).and_then(|cap| cap[1].parse::<f64>().ok());
+            let boundary = if let Some(cap) = RE_JOURNAL_BOOT.captures(line) {
+                Some(cap.get(1).map(|id| id.as_str().to_string()))
+            } else if !fresh && (RE_KERNEL_BANNER.is_match(line) || ts.is_some_and(|ts| ts + 1.0 < last_ts)) {
+                Some(None)
+            } else {
+                None
+            };
+            if let Some(ts) = ts {
+                last_ts = ts;
+            }
+
+            match boundary {
+                Some(id) => {
+                    if let Some(previous) = section.last_mut() {
+                        previous.end = start;
+                    }
+                    section.push(Boot { offset: 0, id, start, end: pos });
+                    fresh = true;
+                }
+                None => {
+                    if !line.trim().is_empty() && !RE_KERNEL_BANNER.is_match(line) {
+                        fresh = false;
+                    }
+                    if let Some(current) = section.last_mut() {
+                        current.end = pos;
+                    }
+                }
+            }
+        }
+        close_section(&mut boots, &mut section, title, section_start, log.len());
+
+        BootMap { boots, sections }
+    }
+
+    pub fn boot_at(&self, pos: usize) -> Option<&Boot> {
+        self.boots.iter().find(|b| b.start <= pos && pos < b.end)
+    }
+
+    /// Boot offset of a byte position; text outside journal/dmesg sections belongs to the current boot.
+    pub fn offset_at(&self, pos: usize) -> i32 {
+        self.boot_at(pos).map_or(0, |b| b.offset)
+    }
+
+    /// Distinct earlier boots; dmesg and journal sections can hold the same boot.
+    pub fn previous_boots(&self) -> usize {
+        let mut offsets: Vec<i32> = self.boots.iter().map(|b| b.offset).filter(|o| *o < 0).collect();
+        offsets.sort();
+        offsets.dedup();
+        offsets.len()
+    }
+
+    /// Counts a finding once when both the dmesg and the journal section log it. Findings are
+    /// keyed by the byte position of their line; per boot and kernel message, only the section
+    /// with the most occurrences is kept. Returns the kept findings in log order.
+    pub fn unique<T>(&self, log: &str, findings: impl IntoIterator<Item = (usize, T)>) -> Vec<T> {
+        let mut groups: HashMap<(i32, &str), Sections<T>> = HashMap::new();
+        for (pos, finding) in findings {
+            let line_start = log[..pos].rfind('\n').map_or(0, |i| i + 1);
+            let line = log[line_start..].lines().next().unwrap_or_default();
+            let section = self.sections.partition_point(|start| *start < pos);
+            let sections = groups.entry((self.offset_at(pos), crate::timeline::kernel_message(line))).or_default();
+            match sections.iter_mut().find(|(s, _)| *s == section) {
+                Some((_, found)) => found.push((pos, finding)),
+                None => sections.push((section, vec![(pos, finding)])),
+            }
+        }
+        let mut kept: Vec<(usize, T)> = groups.into_values()
+            .filter_map(|sections| sections.into_iter().map(|(_, found)| found).reduce(|a, b| if b.len() > a.len() { b } else { a }))
+            .flatten()
+            .collect();
+        kept.sort_by_key(|(pos, _)| *pos);
+        kept.into_iter().map(|(_, finding)| finding).collect()
+    }
+
+    /// Keeps everything except the lines of earlier boots.
+    pub fn current_boot_only(&self, log: &str) -> String {
+        let mut result = String::with_capacity(log.len());
+        let mut pos = 0;
+        for boot in self.boots.iter().filter(|b| b.offset < 0) {
+            result.push_str(&log[pos..boot.start]);
+            pos = boot.end;
+        }
+        result.push_str(&log[pos..]);
+        result
+    }
+}
+
+fn close_section(boots: &mut Vec<Boot>, section: &mut Vec<Boot>, title: Option<&str>, section_start: usize, section_end: usize) {
+    let last = title.and_then(|t| RE_BOOT_ARG.captures(t)).and_then(|cap| cap[1].parse::<i32>().ok()).unwrap_or(0);
+    // A previous-boot section without markers is still one boot, just not the current one
+    if section.is_empty() && last < 0 {
+        boots.push(Boot { offset: last, id: None, start: section_start, end: section_end });
+        return;
+    }
+    let count = section.len() as i32;
+    for (i, mut boot) in section.drain(..).enumerate() {
+        // Lines ahead of the first marker (e.g. a wrapped ring buffer) belong to the first boot
+        if i == 0 {
+            boot.start = section_start;
+        }
+        boot.end = boot.end.min(section_end);
+        boot.offset = last + i as i32 - (count - 1);
+        boots.push(boot);
+    }
+}
+
+pub fn label(offset: i32) -> String {
+    if offset == 0 { "current boot".to_string() } else { format!("boot {}", offset) }
+}
+
+/// Prints how many findings fall into each boot, when they span more than one.
+pub fn print_breakdown(boots: &BootMap, positions: impl Iterator<Item = usize>) {
+    let mut counts: Vec<(i32, usize)> = Vec::new();
+    for offset in positions.map(|pos| boots.offset_at(pos)) {
+        match counts.iter_mut().find(|(o, _)| *o == offset) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((offset, 1)),
+        }
+    }
+    if counts.len() < 2 {
+        return;
+    }
+    counts.sort();
+    let parts: Vec<String> = counts.iter().rev().map(|(offset, count)| format!("{}: {}", label(*offset), count)).collect();
+    println!("   By boot: {}", parts.join(", "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEP: &str = "____________________________________________\n";
+
+    #[test]
+    fn previous_boot_section_without_markers() {
+        let log = format!("{SEP}\n*** journalctl -b -1 -k\n\nOct 13 node kernel: NVRM: Xid 79\n{SEP}\n*** journalctl -b -0 -k\n\nOct 14 node kernel: NVRM: Xid 48\n");
+        let boots = BootMap::parse(&log);
+        assert_eq!(boots.offset_at(log.find("Xid 79").unwrap()), -1);
+        assert_eq!(boots.offset_at(log.find("Xid 48").unwrap()), 0);
+        assert!(!boots.current_boot_only(&log).contains("Xid 79"));
+    }
+
+    #[test]
+    fn boot_markers_and_title_offset() {
+        let log = format!("{SEP}\n*** journalctl -b -1 -k\n-- Boot aa --\nfirst\n-- Boot bb --\nsecond\n");
+        let boots = BootMap::parse(&log);
+        assert_eq!(boots.offset_at(log.find("first").unwrap()), -2);
+        assert_eq!(boots.offset_at(log.find("second").unwrap()), -1);
+        assert_eq!(boots.previous_boots(), 2);
+    }
+
+    #[test]
+    fn dmesg_timestamp_reset_starts_a_boot() {
+        let log = format!("{SEP}\n*** dmesg\n[    0.000000] Linux version 5.15.0\n[  500.000000] old\n[    1.000000] new\n");
+        let boots = BootMap::parse(&log);
+        assert_eq!(boots.offset_at(log.find("old").unwrap()), -1);
+        assert_eq!(boots.offset_at(log.find("new").unwrap()), 0);
+    }
+}
@@ -0,0 +1,152 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::platform::{FormFactor, Platform};
+use crate::CheckResult;
+
+// "*** /proc/cmdline" is followed by an "*** ls:" line in current bug reports
+static RE_PROC_CMDLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"/proc/cmdline\s*\n(?:\*\*\*[^\n]*\n)*\s*([^\n]+)").unwrap());
+static RE_DMESG_CMDLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)(?:Kernel command line|Command line):\s*(.+)$").unwrap());
+
+/// Parameters that take one value, so a repeat overrides the earlier one.
+/// console=, hugepagesz= and hugepages= are meant to be repeated and are not checked for conflicts.
+const SINGLE_VALUED: &[&str] = &["iommu", "nouveau.modeset", "pcie_aspm", "nvidia-drm.modeset"];
+
+/// Parameters taking comma separated options that add up over repeats
+/// (intel_iommu=on intel_iommu=sm_on sets both); only on and off contradict each other.
+const OPTION_LISTS: &[&str] = &["intel_iommu", "amd_iommu"];
+
+/// The kernel command line split into `key[=value]` parameters, in order.
+#[derive(Debug, Clone, Default)]
+pub struct Cmdline {
+    pub raw: String,
+    pub params: Vec<(String, Option<String>)>,
+}
+
+impl Cmdline {
+    pub fn parse(raw: &str) -> Cmdline {
+        let params = raw.split_whitespace()
+            .map(|p| match p.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (p.to_string(), None),
+            })
+            .collect();
+        Cmdline { raw: raw.trim().to_string(), params }
+    }
+
+    /// All values given for `key`; `-` and `_` are interchangeable in module parameters.
+    pub fn values(&self, key: &str) -> Vec<Option<&str>> {
+        let normalize = |k: &str| k.replace('-', "_");
+        self.params.iter()
+            .filter(|(k, _)| normalize(k) == normalize(key))
+            .map(|(_, v)| v.as_deref())
+            .collect()
+    }
+
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values(key).last().copied().flatten()
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        !self.values(key).is_empty()
+    }
+}
+
+pub fn find(log: &str) -> Option<Cmdline> {
+    RE_PROC_CMDLINE.captures(log)
+        .map(|cap| cap[1].to_string())
+        .filter(|raw| raw.contains('='))
+        .or_else(|| RE_DMESG_CMDLINE.captures(log).map(|cap| cap[1].to_string()))
+        .map(|raw| Cmdline::parse(&raw))
+}
+
+pub fn check_kernel_cmdline(log: &str, platform: &Platform) -> CheckResult {
+    println!("\n==================================================");
+    println!("     Kernel Command Line Check");
+    println!("==================================================\n");
+
+    let Some(cmdline) = find(log) else {
+        println!("No kernel command line found");
+        return CheckResult::NotEvaluated("no kernel command line in the input".to_string());
+    };
+    println!("   {}", cmdline.raw);
+
+    let mut issues: Vec<String> = Vec::new();
+    let mut recommendations: Vec<String> = Vec::new();
+
+    // A single-valued parameter given twice with different values: the last one wins, which is rarely intended
+    for key in SINGLE_VALUED {
+        let mut distinct: Vec<_> = cmdline.values(key).iter().map(|v| v.unwrap_or("")).collect();
+        distinct.sort();
+        distinct.dedup();
+        if distinct.len() > 1 {
+            issues.push(format!("Conflicting values for {}: {} (the last one wins)", key, distinct.join(", ")));
+        }
+    }
+    for key in OPTION_LISTS {
+        let values = cmdline.values(key);
+        let options: Vec<&str> = values.iter().flatten().flat_map(|v| v.split(',')).collect();
+        if options.contains(&"on") && options.contains(&"off") {
+            issues.push(format!("Conflicting options for {}: both on and off are given", key));
+        }
+    }
+
+    // IOMMU: needed for interrupt remapping/X2APIC on >255 threads, but full
+    // translation slows GPUDirect RDMA and causes DMAR faults without passthrough.
+    let iommu = cmdline.value("iommu");
+    let intel_iommu = cmdline.value("intel_iommu");
+    let amd_iommu = cmdline.value("amd_iommu");
+    let iommu_off = intel_iommu == Some("off") || amd_iommu == Some("off") || iommu == Some("off");
+    if (intel_iommu.is_some_and(|v| v.contains("on")) || amd_iommu.is_some_and(|v| v.contains("on"))) && iommu != Some("pt") {
+        recommendations.push("IOMMU is enabled without iommu=pt; add iommu=pt so GPUDirect RDMA and P2P traffic bypass DMA translation".to_string());
+    }
+    if iommu_off && log.contains("bad cpu") {
+        issues.push("IOMMU is disabled while the kernel reports 'bad cpu': X2APIC needs IOMMU interrupt remapping for 256+ threads, see the Bad CPU check".to_string());
+    }
+
+    // nouveau must never bind to a datacenter GPU
+    let nouveau_blocked = cmdline.value("nouveau.modeset") == Some("0")
+        || ["rd.driver.blacklist", "modprobe.blacklist", "module_blacklist"].iter()
+            .any(|key| cmdline.value(key).is_some_and(|v| v.split(',').any(|m| m == "nouveau")));
+    if cmdline.value("nouveau.modeset") == Some("1") {
+        issues.push("nouveau.modeset=1 enables nouveau, use nouveau.modeset=0".to_string());
+    } else if !nouveau_blocked {
+        recommendations.push("nouveau is not blocked on the command line; add nouveau.modeset=0 rd.driver.blacklist=nouveau".to_string());
+    }
+
+    // BAR assignment failures are fixed by letting the kernel reassign bridge windows
+    let bar_failure = log.contains("This PCI I/O region assigned to your NVIDIA device is invalid")
+        || log.contains("BAR 1: no space for") || log.contains("BAR 1: failed to assign");
+    let realloc = cmdline.values("pci").into_iter().flatten().flat_map(|v| v.split(',')).find(|v| v.starts_with("realloc"));
+    if bar_failure && realloc.is_none_or(|v| v == "realloc=off") {
+        issues.push("GPU BAR assignment failures in the log and pci=realloc is not set".to_string());
+    }
+
+    match cmdline.value("pcie_aspm") {
+        Some("force") => issues.push("pcie_aspm=force enables link power saving on GPU and NIC links, use pcie_aspm=off".to_string()),
+        None if platform.form_factor == FormFactor::Sxm => recommendations.push("pcie_aspm is not set; pcie_aspm=off avoids link retraining on GPU and NIC links".to_string()),
+        _ => {}
+    }
+
+    if cmdline.value("nvidia-drm.modeset") == Some("1") && platform.form_factor == FormFactor::Sxm {
+        recommendations.push("nvidia-drm.modeset=1 is only needed for displays; headless servers can drop it".to_string());
+    }
+
+    // hugepagesz= alone is valid when huge pages are reserved at runtime through sysfs
+    if cmdline.has("hugepagesz") && !cmdline.has("hugepages") {
+        recommendations.push("hugepagesz is set without hugepages=<count>; no huge pages are reserved at boot, make sure they are reserved at runtime".to_string());
+    }
+
+    for recommendation in &recommendations {
+        println!("   RECOMMENDATION: {}", recommendation);
+    }
+    if issues.is_empty() {
+        println!("\nNo kernel command line problems found");
+    } else {
+        println!("\n** Kernel Command Line Issues: {}", issues.len());
+        for issue in &issues {
+            println!("   {}", issue);
+        }
+    }
+    issues.len().into()
+}
@@ -0,0 +1,286 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::boot::{self, BootMap};
+use crate::pcie::{self, PciTopology};
+use crate::smi;
+
+static RE_XID_DEVICE: Lazy<Regex> = Lazy::new(|| Regex::new(r"NVRM: Xid \(PCI: ?(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2})(?:\.[0-7])?\): (\d+)").unwrap());
+static RE_FALLEN_OFF_BUS_DEVICE: Lazy<Regex> = Lazy::new(|| Regex::new(r"NVRM: GPU (?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7]): GPU has fallen off the bus").unwrap());
+static RE_DRIVER_MISMATCH: Lazy<Regex> = Lazy::new(|| Regex::new(r"NVRM: API mismatch|Please update with matching NVIDIA driver").unwrap());
+
+/// Something one of the checks would report, reduced to what the rules match on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Signal {
+    Xid(u32),
+    FallenOffBus,
+    AerUncorrected,
+    SurpriseDown,
+    DramUncorrectable,
+    RowRemapPending,
+    RowRemapFailure,
+    DriverMismatch,
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Signal::Xid(code) => write!(f, "Xid {}", code),
+            Signal::FallenOffBus => write!(f, "fallen off the bus"),
+            Signal::AerUncorrected => write!(f, "uncorrected AER error"),
+            Signal::SurpriseDown => write!(f, "PCIe Surprise Down"),
+            Signal::DramUncorrectable => write!(f, "DRAM uncorrectable ECC"),
+            Signal::RowRemapPending => write!(f, "row remap pending"),
+            Signal::RowRemapFailure => write!(f, "row remapping failure"),
+            Signal::DriverMismatch => write!(f, "driver version mismatch"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Reseat,
+    ResetGpu,
+    Rma,
+    UpgradeDriver,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Reseat => write!(f, "RESEAT"),
+            Action::ResetGpu => write!(f, "RESET GPU"),
+            Action::Rma => write!(f, "RMA"),
+            Action::UpgradeDriver => write!(f, "UPGRADE DRIVER"),
+        }
+    }
+}
+
+/// A root cause is reported for a device when any `trigger` signal is present;
+/// `support` signals raise the confidence and are consumed with it.
+struct Rule {
+    cause: &'static str,
+    action: Action,
+    advice: &'static str,
+    weight: u32,
+    trigger: fn(Signal) -> bool,
+    support: fn(Signal) -> bool,
+}
+
+static RULES: &[Rule] = &[
+    Rule {
+        cause: "GPU memory row remapping failed",
+        action: Action::Rma,
+        advice: "The GPU has run out of spare rows; drain the node and open an RMA with the GPU serial number.",
+        weight: 95,
+        trigger: |s| matches!(s, Signal::RowRemapFailure | Signal::Xid(64)),
+        support: |s| matches!(s, Signal::DramUncorrectable | Signal::Xid(48) | Signal::Xid(63) | Signal::Xid(94) | Signal::Xid(95)),
+    },
+    Rule {
+        cause: "GPU dropped off the PCIe bus",
+        action: Action::Reseat,
+        advice: "Reseat the GPU/riser and check PCIe cables and slot power; RMA the GPU or baseboard if it happens again.",
+        weight: 90,
+        trigger: |s| matches!(s, Signal::FallenOffBus | Signal::Xid(79)),
+        support: |s| matches!(s, Signal::AerUncorrected | Signal::SurpriseDown | Signal::Xid(154)),
+    },
+    Rule {
+        cause: "Uncorrectable GPU memory error",
+        action: Action::ResetGpu,
+        advice: "Reset the GPU (nvidia-smi -r or reboot) to apply the pending row remap; RMA if uncorrectable errors recur.",
+        weight: 80,
+        trigger: |s| matches!(s, Signal::DramUncorrectable | Signal::Xid(48) | Signal::Xid(94) | Signal::Xid(95)),
+        support: |s| matches!(s, Signal::RowRemapPending | Signal::Xid(63) | Signal::Xid(92)),
+    },
+    Rule {
+        cause: "PCIe link failure",
+        action: Action::Reseat,
+        advice: "Reseat the device and its riser/cable; check the upstream switch port and BIOS PCIe settings.",
+        weight: 70,
+        trigger: |s| matches!(s, Signal::SurpriseDown | Signal::AerUncorrected),
+        support: |s| matches!(s, Signal::SurpriseDown | Signal::AerUncorrected),
+    },
+    Rule {
+        cause: "NVLink fault",
+        action: Action::ResetGpu,
+        advice: "Reset the GPUs and check fabric manager and NVSwitch health; RMA the baseboard if NVLink errors persist.",
+        weight: 65,
+        trigger: |s| matches!(s, Signal::Xid(74)),
+        support: |s| matches!(s, Signal::Xid(45)),
+    },
+    Rule {
+        cause: "GSP firmware timeout",
+        action: Action::UpgradeDriver,
+        advice: "Reset the GPU and move to a newer driver branch; on the proprietary module NVreg_EnableGpuFirmware=0 avoids GSP.",
+        weight: 60,
+        trigger: |s| matches!(s, Signal::Xid(119) | Signal::Xid(120)),
+        support: |_| false,
+    },
+    Rule {
+        cause: "Pending row remap",
+        action: Action::ResetGpu,
+        advice: "Reset the GPU (nvidia-smi -r or reboot) to activate the remapped rows.",
+        weight: 50,
+        trigger: |s| matches!(s, Signal::RowRemapPending | Signal::Xid(63)),
+        support: |s| matches!(s, Signal::RowRemapPending | Signal::Xid(63)),
+    },
+    Rule {
+        cause: "Driver components from different versions",
+        action: Action::UpgradeDriver,
+        advice: "Remove stale driver packages, reinstall one driver version (with matching fabric manager) and reboot.",
+        weight: 40,
+        trigger: |s| matches!(s, Signal::DriverMismatch),
+        support: |s| matches!(s, Signal::Xid(13) | Signal::Xid(31) | Signal::Xid(43)),
+    },
+];
+
+/// Findings of one device (or of the node, when `device` is empty) within one boot.
+#[derive(Debug, Default)]
+struct Group {
+    signals: BTreeMap<Signal, usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RootCause {
+    pub device: String,
+    pub boot: i32,
+    pub cause: &'static str,
+    pub action: Action,
+    pub advice: &'static str,
+    pub score: u32,
+    pub evidence: Vec<(Signal, usize)>,
+}
+
+impl RootCause {
+    pub fn confidence(&self) -> &'static str {
+        if self.evidence.len() > 1 { "high" } else { "medium" }
+    }
+
+    pub fn subject(&self) -> String {
+        if self.device.is_empty() { "Node".to_string() } else { format!("Device {}", self.device) }
+    }
+}
+
+/// Collects per-device, per-boot signals from the log and nvidia-smi output.
+fn collect_groups(log: &str, boots: &BootMap) -> BTreeMap<(String, i32), Group> {
+    let topology = PciTopology::parse(log);
+    let mut groups: BTreeMap<(String, i32), Group> = BTreeMap::new();
+    let mut add = |device: String, pos: usize, signal: Signal| {
+        *groups.entry((device, boots.offset_at(pos))).or_default().signals.entry(signal).or_default() += 1;
+    };
+
+    let mut pos = 0;
+    for line in log.split_inclusive('\n') {
+        let start = pos;
+        pos += line.len();
+        if let Some(cap) = RE_XID_DEVICE.captures(line) {
+            if let Ok(code) = cap[2].parse() {
+                add(format!("{}.0", cap[1].to_lowercase()), start, Signal::Xid(code));
+            }
+        } else if let Some(cap) = RE_FALLEN_OFF_BUS_DEVICE.captures(line) {
+            add(cap[1].to_lowercase(), start, Signal::FallenOffBus);
+        } else if let Some(cap) = pcie::RE_AER_RECEIVED.captures(line).filter(|cap| cap[1].starts_with("Uncorrected")) {
+            add(topology.gpu_for(&cap[2]).unwrap_or_else(|| cap[2].to_lowercase()), start, Signal::AerUncorrected);
+        } else if let Some(cap) = pcie::RE_AER_BIT.captures(line).filter(|cap| cap[2].starts_with("SDES") || cap[2].contains("Surprise Down")) {
+            add(topology.gpu_for(&cap[1]).unwrap_or_else(|| cap[1].to_lowercase()), start, Signal::SurpriseDown);
+        } else if RE_DRIVER_MISMATCH.is_match(line) {
+            add(String::new(), start, Signal::DriverMismatch);
+        }
+    }
+
+    // nvidia-smi reflects the running boot
+    for gpu in smi::parse_gpus(log) {
+        let device = gpu.short_bus_id();
+        let volatile = gpu.root.section(&["ECC Errors", "Volatile"]).and_then(crate::EccCounts::parse);
+        if volatile.is_some_and(|c| c.dram_uncorrectable > 0) {
+            add(device.clone(), log.len(), Signal::DramUncorrectable);
+        }
+        if let Some(rows) = gpu.root.child("Remapped Rows") {
+            if rows.get(&["Pending"]) == Some("Yes") {
+                add(device.clone(), log.len(), Signal::RowRemapPending);
+            }
+            if rows.get(&["Remapping Failure Occurred"]) == Some("Yes") {
+                add(device, log.len(), Signal::RowRemapFailure);
+            }
+        }
+    }
+    groups
+}
+
+/// Applies the rules to every device/boot group, highest scoring first. A
+/// lower ranked cause is dropped when a better one already explains all of its evidence.
+pub fn analyze(log: &str, boots: &BootMap) -> Vec<RootCause> {
+    let mut causes = Vec::new();
+    let groups = collect_groups(log, boots);
+    for ((device, boot), group) in &groups {
+        let (device, boot) = (device.clone(), *boot);
+        // A node-level trigger is supported by the signals of any device in the same boot
+        let mut candidates = group.signals.clone();
+        if device.is_empty() {
+            for (_, other) in groups.iter().filter(|((d, b), _)| !d.is_empty() && *b == boot) {
+                for (signal, count) in &other.signals {
+                    *candidates.entry(*signal).or_default() += count;
+                }
+            }
+        }
+        let mut matched: Vec<RootCause> = RULES.iter()
+            .filter(|rule| group.signals.keys().any(|s| (rule.trigger)(*s)))
+            .map(|rule| {
+                let evidence: Vec<(Signal, usize)> = candidates.iter()
+                    .filter(|(s, _)| ((rule.trigger)(**s) && group.signals.contains_key(s)) || (rule.support)(**s))
+                    .map(|(s, n)| (*s, *n))
+                    .collect();
+                let score = rule.weight + 5 * (evidence.len() as u32 - 1) - 20 * boot.unsigned_abs().min(2);
+                RootCause { device: device.clone(), boot, cause: rule.cause, action: rule.action, advice: rule.advice, score, evidence }
+            })
+            .collect();
+        matched.sort_by_key(|c| std::cmp::Reverse(c.score));
+
+        let mut explained: Vec<Signal> = Vec::new();
+        for cause in matched {
+            if cause.evidence.iter().all(|(s, _)| explained.contains(s)) {
+                continue;
+            }
+            explained.extend(cause.evidence.iter().map(|(s, _)| *s));
+            causes.push(cause);
+        }
+    }
+    causes.sort_by(|a, b| b.score.cmp(&a.score).then(a.device.cmp(&b.device)));
+    causes
+}
+
+pub fn print_root_causes(causes: &[RootCause]) {
+    println!("\n==================================================");
+    println!("     Probable Root Cause");
+    println!("==================================================\n");
+
+    if causes.is_empty() {
+        println!("No correlated incidents found");
+        return;
+    }
+
+    for (rank, cause) in causes.iter().enumerate() {
+        println!("{}. {} ({}): {} [{} confidence]", rank + 1, cause.subject(), boot::label(cause.boot), cause.cause, cause.confidence());
+        let evidence: Vec<String> = cause.evidence.iter().map(|(s, n)| format!("{} ({}x)", s, n)).collect();
+        println!("   Evidence: {}", evidence.join(", "));
+        println!("   Action: {} - {}", cause.action, cause.advice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn driver_mismatch_is_supported_by_device_xids() {
+        let log = "[  10.000000] NVRM: API mismatch: the client has the version 550.127.05, but this kernel module has the version 550.144.03.\n\
+            [  20.000000] NVRM: Xid (PCI:0000:3b:00): 13, pid=4242, Graphics Exception\n";
+        let causes = analyze(log, &BootMap::parse(log));
+        let mismatch = causes.iter().find(|c| c.device.is_empty()).unwrap();
+        assert_eq!(mismatch.cause, "Driver components from different versions");
+        assert_eq!(mismatch.evidence, vec![(Signal::Xid(13), 1), (Signal::DriverMismatch, 1)]);
+        assert_eq!(mismatch.confidence(), "high");
+    }
+}
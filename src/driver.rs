@@ -0,0 +1,275 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::cmp::Ordering;
+
+use crate::CheckResult;
+
+static RE_KERNEL_MODULE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^NVRM version:.*?Kernel Module(?: for \S+)?\s+(\d+\.\d+(?:\.\d+)?)").unwrap());
+static RE_SMI_DRIVER: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^Driver Version\s*:\s*(\d+\.\d+(?:\.\d+)?)").unwrap());
+static RE_SMI_CUDA: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^CUDA Version\s*:\s*(\d+\.\d+)").unwrap());
+static RE_LIBRARY: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(libcuda|libnvidia-ml)\.so\.(\d{3}\.\d+(?:\.\d+)?)\b").unwrap());
+// dpkg -l: "ii  nvidia-driver-550  550.144.03-0ubuntu1  amd64 ..."; rpm -qa: "nvidia-driver-550.144.03-1.el9.x86_64"
+static RE_DPKG_PACKAGE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^ii\s+((?:nvidia-driver|nvidia-utils|libnvidia-compute|nvidia-kernel-common|nvidia-dkms|nvidia-fabricmanager|cuda-drivers)[\w.-]*?)(?::\w+)?\s+(\d{3}\.\d+(?:\.\d+)?)").unwrap());
+static RE_RPM_PACKAGE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^((?:nvidia-driver|nvidia-fabric-?manager|kmod-nvidia|cuda-drivers)[\w-]*?)-(\d{3}\.\d+(?:\.\d+)?)-").unwrap());
+static RE_FABRIC_MANAGER: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)Fabric Manager version(?: is)?\s*:?\s*(\d{3}\.\d+(?:\.\d+)?)").unwrap());
+static RE_API_MISMATCH: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^.*(?:Driver/library version mismatch|NVRM: API mismatch).*$").unwrap());
+
+/// Component of the driver stack and the version it reports.
+#[derive(Debug, Clone)]
+pub struct DriverComponent {
+    pub source: String,
+    pub version: String,
+    pub userspace: bool,
+}
+
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    // Numeric prefix as a number, the rest as text: BIOS "1.4a" is after "1.4" and before "1.5"
+    let parse = |v: &str| v.split('.')
+        .map(|part| {
+            let digits = part.len() - part.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            (part[..digits].parse::<u64>().unwrap_or(0), part[digits..].to_lowercase())
+        })
+        .collect::<Vec<_>>();
+    parse(a).cmp(&parse(b))
+}
+
+/// Kernel module version from /proc/driver/nvidia/version, i.e. what is actually loaded.
+pub fn kernel_module_version(log: &str) -> Option<String> {
+    RE_KERNEL_MODULE.captures(log).map(|cap| cap[1].to_string())
+}
+
+pub fn collect_components(log: &str) -> Vec<DriverComponent> {
+    let mut components = Vec::new();
+    let mut push = |source: String, version: &str, userspace: bool| {
+        if !components.iter().any(|c: &DriverComponent| c.source == source && c.version == version) {
+            components.push(DriverComponent { source, version: version.to_string(), userspace });
+        }
+    };
+
+    if let Some(version) = kernel_module_version(log) {
+        push("Kernel module (/proc/driver/nvidia/version)".to_string(), &version, false);
+    }
+    for cap in RE_SMI_DRIVER.captures_iter(log) {
+        push("nvidia-smi Driver Version".to_string(), &cap[1], true);
+    }
+    for cap in RE_LIBRARY.captures_iter(log) {
+        push(format!("{}.so", &cap[1]), &cap[2], true);
+    }
+    for cap in RE_DPKG_PACKAGE.captures_iter(log).chain(RE_RPM_PACKAGE.captures_iter(log)) {
+        push(format!("package {}", &cap[1]), &cap[2], true);
+    }
+    for cap in RE_FABRIC_MANAGER.captures_iter(log) {
+        push("nvidia-fabricmanager".to_string(), &cap[1], true);
+    }
+    components
+}
+
+pub fn check_driver_stack(log: &str) -> CheckResult {
+    println!("\n==================================================");
+    println!("     Driver Stack Consistency Check");
+    println!("==================================================\n");
+
+    let components = collect_components(log);
+    let api_mismatches: Vec<&str> = RE_API_MISMATCH.find_iter(log).map(|m| m.as_str().trim()).collect();
+
+    if components.is_empty() && api_mismatches.is_empty() {
+        println!("No driver version information found");
+        return CheckResult::NotEvaluated("no driver version information in the input".to_string());
+    }
+
+    for component in &components {
+        println!("   {:<48} {}", component.source, component.version);
+    }
+    if let Some(cap) = RE_SMI_CUDA.captures(log) {
+        println!("   {:<48} {}", "CUDA Version (max supported by driver)", &cap[1]);
+    }
+
+    let mut issues = Vec::new();
+    let kernel = components.iter().find(|c| !c.userspace);
+    if let Some(kernel) = kernel {
+        let mut mismatched: Vec<(&str, Vec<&str>)> = Vec::new();
+        for component in components.iter().filter(|c| c.userspace && c.version != kernel.version) {
+            match mismatched.iter_mut().find(|(version, _)| *version == component.version) {
+                Some((_, sources)) => sources.push(&component.source),
+                None => mismatched.push((&component.version, vec![&component.source])),
+            }
+        }
+        for (version, sources) in mismatched {
+            let relation = match compare_versions(&kernel.version, version) {
+                Ordering::Less => "older",
+                Ordering::Greater => "newer",
+                // Spelled differently (550.144.3 vs 550.144.03) but the same version
+                Ordering::Equal => continue,
+            };
+            issues.push(format!("Kernel module {} is {} than userspace {} ({})", kernel.version, relation, version, sources.join(", ")));
+        }
+    }
+    if kernel.is_none() {
+        let mut versions: Vec<&str> = components.iter().map(|c| c.version.as_str()).collect();
+        versions.sort_by(|a, b| compare_versions(a, b));
+        versions.dedup();
+        if versions.len() > 1 {
+            issues.push(format!("Userspace components report different driver versions: {}", versions.join(", ")));
+        }
+    }
+
+    if !api_mismatches.is_empty() {
+        let counts = crate::count_occurrences(api_mismatches.iter());
+        for (line, count) in counts {
+            issues.push(format!("({}x) {}", count, line));
+        }
+    }
+
+    if issues.is_empty() {
+        println!("\nAll driver stack components report the same version");
+    } else {
+        println!("\n** Driver Stack Mismatches: {}", issues.len());
+        for issue in &issues {
+            println!("   {}", issue);
+        }
+        println!("   A kernel module that differs from the userspace libraries causes the classic");
+        println!("   \"Driver/library version mismatch\" after an incomplete upgrade: reboot, or unload and reload the nvidia modules.");
+    }
+    issues.len().into()
+}
+
+static RE_LOADED_MODULE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(nvidia|nvidia_uvm|nvidia_drm|nvidia_modeset|nvidia_peermem|nouveau)\s+\d+\s+\d+").unwrap());
+static RE_MODINFO_LICENSE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^license:\s*(.+)$").unwrap());
+static RE_NOUVEAU_ACTIVE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^.*(?:\[drm\] Initialized nouveau|nouveau \d{4}:[0-9a-f]{2}:[0-9a-f]{2}\.\d: (?:NVIDIA|DRM|bios|fb)).*$").unwrap());
+static RE_TAINT: Lazy<Regex> = Lazy::new(|| Regex::new(r"nvidia\w*: (?:module license '[^']+' taints kernel|loading out-of-tree module taints kernel|module verification failed[^\n]*)").unwrap());
+static RE_REQUIRES_OPEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^.*NVRM:.*requires use of the NVIDIA open kernel modules.*$").unwrap());
+static RE_DKMS_STATUS: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(nvidia(?:-fs|-peermem)?)[/,]\s*([\d.]+),\s*(\S+?),\s*\S+?:\s*(.+)$").unwrap());
+static RE_DKMS_ERROR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^.*(?:Error! Bad return status for module build|Building module[^\n]*\(bad exit status|Error! Build of nvidia|dkms[^\n]*autoinstall[^\n]*failed).*$").unwrap());
+static RE_RUNNING_KERNEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^uname:\s*\S+\s+\S+\s+(\S+)").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleFlavour {
+    Open,
+    Proprietary,
+    Unknown,
+}
+
+/// Open and proprietary modules share a name; /proc/driver/nvidia/version and
+/// the modinfo license tell them apart.
+pub fn module_flavour(log: &str) -> ModuleFlavour {
+    if log.lines().any(|l| l.starts_with("NVRM version:") && l.contains("Open Kernel Module")) {
+        return ModuleFlavour::Open;
+    }
+    match RE_MODINFO_LICENSE.captures(log).map(|cap| cap[1].trim().to_string()) {
+        Some(license) if license == "NVIDIA" => ModuleFlavour::Proprietary,
+        Some(license) if license.contains("MIT/GPL") => ModuleFlavour::Open,
+        _ if log.lines().any(|l| l.starts_with("NVRM version:")) => ModuleFlavour::Proprietary,
+        _ => ModuleFlavour::Unknown,
+    }
+}
+
+pub fn check_kernel_modules(log: &str) -> CheckResult {
+    println!("\n==================================================");
+    println!("     Kernel Module Check");
+    println!("==================================================\n");
+
+    let mut loaded: Vec<&str> = RE_LOADED_MODULE.captures_iter(log).map(|cap| cap.get(1).unwrap().as_str()).collect();
+    loaded.sort();
+    loaded.dedup();
+    let flavour = module_flavour(log);
+    let mut issues = Vec::new();
+
+    println!("   Loaded modules: {}", if loaded.is_empty() { "not found in lsmod output".to_string() } else { loaded.join(", ") });
+    println!("   NVIDIA module flavour: {}", match flavour {
+        ModuleFlavour::Open => "open (Dual MIT/GPL)",
+        ModuleFlavour::Proprietary => "proprietary",
+        ModuleFlavour::Unknown => "unknown",
+    });
+
+    let nouveau_messages: Vec<&str> = RE_NOUVEAU_ACTIVE.find_iter(log).map(|m| m.as_str().trim()).collect();
+    if loaded.contains(&"nouveau") || !nouveau_messages.is_empty() {
+        issues.push("nouveau is loaded or bound to a GPU, blacklist it (nouveau.modeset=0 rd.driver.blacklist=nouveau) and rebuild the initramfs".to_string());
+        for line in nouveau_messages.iter().take(3) {
+            println!("      {}", line);
+        }
+    }
+    if !loaded.is_empty() && !loaded.contains(&"nvidia") {
+        issues.push("The nvidia kernel module is not loaded".to_string());
+    }
+
+    if let Some(m) = RE_REQUIRES_OPEN.find(log) {
+        if flavour != ModuleFlavour::Open {
+            issues.push(format!("GPU requires the open kernel module but the proprietary one is installed: {}", m.as_str().trim()));
+        }
+    }
+
+    let taints = crate::count_occurrences(RE_TAINT.find_iter(log).map(|m| m.as_str().trim()));
+    if !taints.is_empty() {
+        println!("   Kernel taint messages (expected for out-of-tree modules):");
+        for (line, count) in taints {
+            println!("      ({}x) {}", count, line);
+        }
+    }
+
+    // (module, version, kernel, status)
+    let dkms: Vec<(&str, &str, &str, &str)> = RE_DKMS_STATUS.captures_iter(log)
+        .map(|cap| (cap.get(1).unwrap().as_str(), cap.get(2).unwrap().as_str(), cap.get(3).unwrap().as_str(), cap.get(4).unwrap().as_str().trim()))
+        .collect();
+    if !dkms.is_empty() {
+        println!("   DKMS:");
+        for (module, version, kernel, status) in &dkms {
+            println!("      {} {} for {}: {}", module, version, kernel, status);
+        }
+        // The driver itself decides whether the running kernel is covered; nvidia-fs and
+        // nvidia-peermem are optional add-ons reported on their own
+        if let Some(running) = RE_RUNNING_KERNEL.captures(log).map(|cap| cap.get(1).unwrap().as_str()) {
+            let mut modules: Vec<&str> = dkms.iter().map(|(module, ..)| *module).collect();
+            modules.sort();
+            modules.dedup();
+            for module in modules {
+                let built = dkms.iter().any(|(m, _, kernel, status)| *m == module && *kernel == running && status.starts_with("installed"));
+                if !built && module == "nvidia" {
+                    issues.push(format!("DKMS has no installed nvidia module for the running kernel {}", running));
+                } else if !built {
+                    issues.push(format!("DKMS has no installed {} module for the running kernel {} (GPUDirect add-on, the driver itself is unaffected)", module, running));
+                }
+            }
+        }
+    }
+    for (line, count) in crate::count_occurrences(RE_DKMS_ERROR.find_iter(log).map(|m| m.as_str().trim())) {
+        issues.push(format!("DKMS build failure ({}x): {}", count, line));
+    }
+
+    if issues.is_empty() && loaded.is_empty() && flavour == ModuleFlavour::Unknown && dkms.is_empty() {
+        println!("\nNo lsmod, module or DKMS information found");
+        return CheckResult::NotEvaluated("no lsmod or kernel module information in the input".to_string());
+    }
+    if issues.is_empty() {
+        println!("\nNo kernel module conflicts found");
+    } else {
+        println!("\n** Kernel Module Issues: {}", issues.len());
+        for issue in &issues {
+            println!("   {}", issue);
+        }
+    }
+    issues.len().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_suffixes() {
+        assert_eq!(compare_versions("1.4a", "1.4"), Ordering::Greater);
+        assert_eq!(compare_versions("1.4a", "1.5"), Ordering::Less);
+        assert_eq!(compare_versions("1.4a", "1.4b"), Ordering::Less);
+        assert_eq!(compare_versions("550.144.03", "550.54.15"), Ordering::Greater);
+        assert_eq!(compare_versions("535.161.08", "535.161.08"), Ordering::Equal);
+    }
+
+    #[test]
+    fn dkms_running_kernel_is_decided_by_the_driver_module() {
+        let log = "uname: Linux node 5.15.0-119-generic #129-Ubuntu SMP\n\
+            nvidia/550.144.03, 5.15.0-119-generic, x86_64: installed\n\
+            nvidia-peermem/550.144.03, 5.15.0-105-generic, x86_64: installed\n";
+        assert_eq!(check_kernel_modules(log), CheckResult::Issues(1));
+        let log = log.replace("nvidia-peermem/550.144.03, 5.15.0-105", "nvidia-peermem/550.144.03, 5.15.0-119");
+        assert_eq!(check_kernel_modules(&log), CheckResult::Ok);
+    }
+}
@@ -0,0 +1,172 @@
+use flate2::read::GzDecoder;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+static RE_BUG_REPORT_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|/)nvidia-bug-report[^/]*\.log(?:\.gz)?$").unwrap());
+static RE_HAS_DMESG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\[\s*\d+\.\d+\]").unwrap());
+static RE_HAS_LSPCI: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:[0-9a-fA-F]{4}:)?[0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7] \w").unwrap());
+static RE_HAS_KERNEL_LOG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*\[\s*\d+\.\d+\]|^\s*\[\w{3} \w{3}\s+\d+ [\d:]+ \d{4}\]| kernel: ").unwrap());
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Tells whether the bug report already has a section.
+type SectionPresent = fn(&str) -> bool;
+
+/// sosreport command outputs (path inside sos_commands/) used when the bug report lacks the section.
+const SOS_SECTIONS: &[(&str, SectionPresent)] = &[
+    ("kernel/dmesg", |log| RE_HAS_DMESG.is_match(log)),
+    ("pci/lspci_-nnvv", |log| RE_HAS_LSPCI.is_match(log)),
+    ("hardware/dmidecode", |log| log.contains("DMI type")),
+    ("nvidia/nvidia-smi_-q", |log| crate::smi::RE_GPU_SMI_BLOCK.is_match(log)),
+];
+
+/// Kinds of data the checks work from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Data {
+    KernelLog,
+    Smi,
+    Lspci,
+}
+
+impl Data {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Data::KernelLog => "kernel log (dmesg/journal)",
+            Data::Smi => "nvidia-smi -q output",
+            Data::Lspci => "lspci output",
+        }
+    }
+}
+
+/// Which kinds of data the input contains, so checks without their data
+/// can be reported as not evaluated instead of finding nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    available: Vec<Data>,
+}
+
+impl Coverage {
+    pub fn detect(log: &str) -> Coverage {
+        let mut available = Vec::new();
+        if RE_HAS_KERNEL_LOG.is_match(log) {
+            available.push(Data::KernelLog);
+        }
+        if crate::smi::RE_GPU_SMI_BLOCK.is_match(log) {
+            available.push(Data::Smi);
+        }
+        if RE_HAS_LSPCI.is_match(log) {
+            available.push(Data::Lspci);
+        }
+        Coverage { available }
+    }
+
+    pub fn has(&self, data: Data) -> bool {
+        self.available.contains(&data)
+    }
+}
+
+/// The text to analyze and where it came from.
+pub struct Input {
+    pub content: String,
+    pub sources: Vec<String>,
+    /// Length of the bug report itself, sections appended after it came from other files.
+    pub report_len: usize,
+}
+
+/// Reads a bug report from a file, `-` for stdin, a gzip/xz compressed log,
+/// or a tar/tar.gz/tar.xz archive such as a sosreport.
+pub fn read(path: &Path) -> Result<Input, Box<dyn Error>> {
+    let name = path.display().to_string();
+    let raw = if name == "-" {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        data
+    } else {
+        fs::read(path)?
+    };
+    let data = decompress(raw)?;
+
+    if is_tar(&data) {
+        return read_archive(&data, &name);
+    }
+    let content = String::from_utf8_lossy(&data).into_owned();
+    Ok(Input { report_len: content.len(), content, sources: vec![name] })
+}
+
+fn decompress(data: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    if data.starts_with(GZIP_MAGIC) {
+        let mut out = Vec::new();
+        GzDecoder::new(data.as_slice()).read_to_end(&mut out)?;
+        Ok(out)
+    } else if data.starts_with(XZ_MAGIC) {
+        let mut out = Vec::new();
+        lzma_rs::xz_decompress(&mut data.as_slice(), &mut out).map_err(|e| format!("xz: {:?}", e))?;
+        Ok(out)
+    } else {
+        Ok(data)
+    }
+}
+
+fn is_tar(data: &[u8]) -> bool {
+    data.get(257..262) == Some(b"ustar".as_slice())
+}
+
+fn read_archive(data: &[u8], name: &str) -> Result<Input, Box<dyn Error>> {
+    let mut report: Option<(String, String)> = None;
+    let mut sos_files: Vec<(String, String)> = Vec::new();
+
+    for entry in tar::Archive::new(data).entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let wanted_sos = SOS_SECTIONS.iter().any(|(suffix, _)| path.ends_with(&format!("sos_commands/{}", suffix)));
+        if (report.is_none() && RE_BUG_REPORT_NAME.is_match(&path)) || wanted_sos {
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            let text = String::from_utf8_lossy(&decompress(bytes)?).into_owned();
+            if wanted_sos {
+                sos_files.push((path, text));
+            } else {
+                report = Some((path, text));
+            }
+        }
+    }
+
+    if report.is_none() && sos_files.is_empty() {
+        return Err(format!("no nvidia-bug-report.log or sosreport command output found in '{}'", name).into());
+    }
+    let (mut content, mut sources) = match report {
+        Some((path, text)) => (text, vec![format!("{}:{}", name, path)]),
+        None => {
+            eprintln!("Warning: no nvidia-bug-report.log in '{}', analyzing sosreport files only", name);
+            (String::new(), Vec::new())
+        }
+    };
+
+    let report_len = content.len();
+
+    // Append sosreport outputs as extra report sections, only where the bug report has none
+    for (suffix, present) in SOS_SECTIONS {
+        let Some((path, text)) = sos_files.iter().find(|(path, _)| path.ends_with(&format!("sos_commands/{}", suffix))) else {
+            continue;
+        };
+        if present(&content) {
+            continue;
+        }
+        append_section(&mut content, &format!("{} (sosreport)", path), text);
+        sources.push(format!("{}:{}", name, path));
+    }
+    Ok(Input { content, sources, report_len })
+}
+
+/// Adds `text` as a report section, the way nvidia-bug-report.sh separates command outputs.
+pub fn append_section(content: &mut String, title: &str, text: &str) {
+    content.push_str(&format!("\n____________________________________________\n\n*** {}\n\n{}\n", title, text));
+}
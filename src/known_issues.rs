@@ -0,0 +1,190 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::driver::compare_versions;
+use crate::platform::Platform;
+use crate::CheckResult;
+
+/// Built-in entries; a file passed with --known-issues-csv adds to them.
+const BUILTIN: &str = include_str!("../known-issues.csv");
+
+static RE_DMI_BIOS: Lazy<Regex> = Lazy::new(|| Regex::new(r"DMI:[^\n]*, BIOS (\S+)").unwrap());
+static RE_CONSTRAINT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(<=|>=|<|>|=)?\s*(\S+)$").unwrap());
+
+/// A known issue from an earlier incident. Empty conditions match anything.
+#[derive(Debug, Clone)]
+pub struct KnownIssue {
+    pub id: String,
+    pub product: String,
+    pub bios: String,
+    pub driver: String,
+    pub gpu: String,
+    pub signature: Option<Regex>,
+    pub issue: String,
+    pub fix: String,
+}
+
+/// What the known issues are matched against.
+#[derive(Debug, Default)]
+struct Facts {
+    product: String,
+    bios: String,
+    driver: String,
+    gpu_models: Vec<String>,
+}
+
+impl Facts {
+    fn gather(log: &str, platform: &Platform) -> Facts {
+        let product = crate::RE_SYS_INFO.captures(log)
+            .map(|block| crate::extract_field(block.get(1).unwrap().as_str(), "Product Name:").to_string())
+            .filter(|p| !p.is_empty())
+            .or_else(|| crate::RE_CHASSIS_DMI.captures(log).map(|cap| cap[1].to_string()))
+            .unwrap_or_default();
+        let bios = crate::RE_BIOS_INFO.captures(log)
+            .map(|block| crate::extract_field(block.get(1).unwrap().as_str(), "Version:").to_string())
+            .filter(|v| !v.is_empty())
+            .or_else(|| RE_DMI_BIOS.captures(log).map(|cap| cap[1].to_string()))
+            .unwrap_or_default();
+        let driver = crate::driver::kernel_module_version(log)
+            .or_else(|| crate::RE_NVIDIA_VERSION.captures(log).map(|cap| cap[1].trim().to_string()))
+            .unwrap_or_default();
+        Facts { product, bios, driver, gpu_models: platform.gpu_models.clone() }
+    }
+}
+
+/// `constraint` is a comma separated list of comparisons, or a bare version
+/// prefix ("550" matches "550.127.05").
+fn version_matches(version: &str, constraint: &str) -> bool {
+    if constraint.is_empty() {
+        return true;
+    }
+    if version.is_empty() {
+        return false;
+    }
+    constraint.split(',').map(str::trim).all(|part| {
+        let Some(cap) = RE_CONSTRAINT.captures(part) else { return false };
+        let wanted = &cap[2];
+        let ord = compare_versions(version, wanted);
+        match cap.get(1).map(|m| m.as_str()) {
+            Some("<") => ord == Ordering::Less,
+            Some("<=") => ord != Ordering::Greater,
+            Some(">") => ord == Ordering::Greater,
+            Some(">=") => ord != Ordering::Less,
+            Some("=") => ord == Ordering::Equal,
+            _ => version == wanted || version.starts_with(&format!("{}.", wanted)),
+        }
+    })
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+impl KnownIssue {
+    fn matches(&self, log: &str, facts: &Facts) -> bool {
+        (self.product.is_empty() || contains(&facts.product, &self.product))
+            && version_matches(&facts.bios, &self.bios)
+            && version_matches(&facts.driver, &self.driver)
+            && (self.gpu.is_empty() || facts.gpu_models.iter().any(|m| contains(m, &self.gpu)))
+            && self.signature.as_ref().is_none_or(|re| re.is_match(log))
+    }
+
+    fn conditions(&self) -> Vec<String> {
+        let mut conditions = Vec::new();
+        for (name, value) in [("product", &self.product), ("BIOS", &self.bios), ("driver", &self.driver), ("GPU", &self.gpu)] {
+            if !value.is_empty() {
+                conditions.push(format!("{} {}", name, value));
+            }
+        }
+        if let Some(re) = &self.signature {
+            conditions.push(format!("log /{}/", re.as_str()));
+        }
+        conditions
+    }
+}
+
+fn parse_issues<R: std::io::Read>(reader: R, source: &str) -> Vec<KnownIssue> {
+    let mut reader = csv::ReaderBuilder::new().has_headers(true).comment(Some(b'#')).flexible(true).from_reader(reader);
+    let mut issues = Vec::new();
+    for record in reader.records().flatten() {
+        let field = |i: usize| record.get(i).unwrap_or("").trim().to_string();
+        let signature = match field(5) {
+            s if s.is_empty() => None,
+            s => match Regex::new(&s) {
+                Ok(re) => Some(re),
+                Err(err) => {
+                    eprintln!("Warning: known issue '{}' in {} has an invalid signature: {}", field(0), source, err);
+                    continue;
+                }
+            },
+        };
+        let issue = KnownIssue {
+            id: field(0),
+            product: field(1),
+            bios: field(2),
+            driver: field(3),
+            gpu: field(4),
+            signature,
+            issue: field(6),
+            fix: field(7),
+        };
+        // An entry without any condition would match every report
+        if !issue.conditions().is_empty() {
+            issues.push(issue);
+        }
+    }
+    issues
+}
+
+pub fn load(path: Option<&Path>) -> Vec<KnownIssue> {
+    let mut issues = parse_issues(BUILTIN.as_bytes(), "known-issues.csv");
+    if let Some(path) = path {
+        match std::fs::File::open(path) {
+            Ok(file) => issues.extend(parse_issues(file, &path.display().to_string())),
+            Err(err) => eprintln!("Warning: could not read known issues CSV '{}': {}", path.display(), err),
+        }
+    }
+    issues
+}
+
+pub fn check_known_issues(log: &str, platform: &Platform, issues: &[KnownIssue]) -> CheckResult {
+    println!("\n==================================================");
+    println!("     Known Issues Check");
+    println!("==================================================\n");
+
+    let facts = Facts::gather(log, platform);
+    // Every known issue names a driver or GPU; without either, nothing can be ruled out
+    if facts.driver.is_empty() && facts.gpu_models.is_empty() {
+        println!("Known issues not evaluated: no driver version or GPU model found");
+        return CheckResult::NotEvaluated("no driver version or GPU model in the input".to_string());
+    }
+    let matched: Vec<&KnownIssue> = issues.iter().filter(|issue| issue.matches(log, &facts)).collect();
+    if matched.is_empty() {
+        println!("No known issues match this system ({} checked)", issues.len());
+        return CheckResult::Ok;
+    }
+
+    println!("** Known Issues: {}", matched.len());
+    for issue in &matched {
+        println!("   [{}] {}", issue.id, issue.issue);
+        println!("      Fix: {}", issue.fix);
+        println!("      Matched: {}", issue.conditions().join(", "));
+    }
+    matched.len().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bios_with_letter_suffix() {
+        assert!(!version_matches("1.4a", "<1.4"));
+        assert!(version_matches("1.3b", "<1.4"));
+        assert!(version_matches("550.127.05", "550"));
+        assert!(version_matches("535.104.05", ">=535.54,<535.161"));
+        assert!(!version_matches("5501.1", "550"));
+    }
+}
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::HashMap;
@@ -6,92 +6,276 @@ use std::error::Error;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use input::Data::{KernelLog, Lspci, Smi};
+
+/// The report is printed through `output`, which applies --redact on the way out
+/// so the checks still analyze the original log.
+macro_rules! println {
+    () => {
+        $crate::output::print(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!($($arg)*))
+    };
+}
+
+mod boot;
+mod cmdline;
+mod correlate;
+mod driver;
+mod input;
+mod known_issues;
+mod mce;
+mod output;
+mod pcie;
+mod platform;
+mod quality;
+mod redact;
+mod remediation;
+mod smi;
+mod timeline;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = "Parses an nvidia-bug-report.log file to provide a summary of system info and errors.")]
 struct Args {
-    #[arg(default_value = "nvidia-bug-report.log")]
-    log_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Bug report log (plain, .gz or .xz), tar/tar.gz/tar.xz archive or sosreport, or - for stdin.
+    /// Defaults to nvidia-bug-report.log unless only --dmesg/--journal/--smi-q/--lspci are given
+    log_file: Option<PathBuf>,
+
+    /// Standalone dmesg (or dmesg -T) output
+    #[arg(long)]
+    dmesg: Option<PathBuf>,
+
+    /// Standalone journalctl output
+    #[arg(long)]
+    journal: Option<PathBuf>,
+
+    /// Standalone nvidia-smi -q output
+    #[arg(long)]
+    smi_q: Option<PathBuf>,
+
+    /// Standalone lspci output (-nn or -vvv)
+    #[arg(long)]
+    lspci: Option<PathBuf>,
 
     #[arg(long, short = 'x', default_value = "xid-errors.csv")]
     xid_errors_csv: PathBuf,
+
+    /// Number of GPUs the system should have; missing ones are reported
+    #[arg(long)]
+    expected_gpus: Option<usize>,
+
+    /// Print a chronological timeline of the events found by the checks
+    #[arg(long)]
+    timeline: bool,
+
+    /// Limit the timeline to events within this many seconds of the first critical event
+    #[arg(long, value_name = "SECONDS")]
+    timeline_window: Option<f64>,
+
+    /// Only analyze the current (last) boot of multi-boot journal and dmesg sections
+    #[arg(long)]
+    current_boot: bool,
+
+    /// Extra remediation entries (key,requires,action,link) added to or overriding the built-in ones
+    #[arg(long)]
+    remediation_csv: Option<PathBuf>,
+
+    /// Extra known issues (id,product,bios,driver,gpu,signature,issue,fix) checked along with the built-in ones
+    #[arg(long)]
+    known_issues_csv: Option<PathBuf>,
+
+    /// Replace hostnames, IP/MAC addresses, serial numbers, UUIDs and usernames with stable tokens
+    #[arg(long)]
+    redact: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write a sanitized copy of a log for sharing outside the company
+    Redact {
+        /// Same inputs as the main command, including - for stdin
+        input: PathBuf,
+
+        /// Defaults to the input file name with a .redacted suffix, or stdout for stdin
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
 }
 
 static RE_DATE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^Date:\s*(.*)$").unwrap());
-static RE_NVIDIA_VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^Driver Version:\s*(.*)$").unwrap());
+static RE_NVIDIA_VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^Driver Version\s*:\s*(.*)$").unwrap());
 static RE_CHASSIS_DMI: Lazy<Regex> = Lazy::new(|| Regex::new(r"DMI:\s*(.*),").unwrap());
 static RE_SYS_INFO: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?ms)System Information\s*\n(.*?)\n\n").unwrap());
 static RE_BASEBOARD_INFO: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?ms)Base Board Information\s*\n(.*?)\n\n").unwrap());
 static RE_BIOS_INFO: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?ms)BIOS Information\s*\n(.*?)\n\n").unwrap());
-static RE_CMDLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"cmdline\s*\n\s*(.*)").unwrap());
 static RE_CPU_MODEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^model name\s*:\s*(.*)$").unwrap());
 static RE_DIMM: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?ms)Handle.*?DMI type 17, (.*?)\n\n").unwrap());
 static RE_GPU_BLOCK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?ms)GPU \d+:(.*?)\n\n").unwrap());
-static RE_GPU_SMI_BLOCK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^GPU ([0-9A-Fa-f:\.]+)\s*$").unwrap());
 static RE_GPU_UUID: Lazy<Regex> = Lazy::new(|| Regex::new(r"GPU UUID\s*:\s*([^\s]+)").unwrap());
 static RE_BUS_LOCATION: Lazy<Regex> = Lazy::new(|| Regex::new(r"Bus Location:\s*([^\s]+)").unwrap());
 static RE_GPU_MODEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"Model:\s*([^\n]+)").unwrap());
 static RE_SUBSYSTEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"Subsystem:\s*([^\n]+)").unwrap());
-static RE_XID: Lazy<Regex> = Lazy::new(|| Regex::new(r"kernel: NVRM: Xid \(PCI: [^)]+\): (\d+), ([^\n]+)").unwrap());
+static RE_LSPCI_NVIDIA_GPU: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7])\s+(?:3D controller|VGA compatible controller|Display controller)[^:]*:\s*NVIDIA.*$").unwrap());
+static RE_SMI_RAN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)NVSMI LOG|^Attached GPUs\s*:|No devices were found").unwrap());
+// Journal lines carry "kernel: ", dmesg lines a [timestamp]; drivers print "PCI:0000" or "PCI: 0000"
+static RE_XID: Lazy<Regex> = Lazy::new(|| Regex::new(r"NVRM: Xid \(PCI: ?[^)]+\): (\d+), ([^\n]+)").unwrap());
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
-    if !args.log_file.exists() {
-        eprintln!("Error: Log file not found at '{}'", args.log_file.display());
+    if let Some(Command::Redact { input, output }) = &args.command {
+        return redact_file(input, output.as_deref());
+    }
+
+    let artifacts = [(&args.dmesg, "dmesg"), (&args.journal, "journalctl"), (&args.smi_q, "nvidia-smi -q"), (&args.lspci, "lspci")];
+    let log_file = match &args.log_file {
+        Some(path) => Some(path.clone()),
+        None if artifacts.iter().all(|(path, _)| path.is_none()) => Some(PathBuf::from("nvidia-bug-report.log")),
+        None => None,
+    };
+    if let Some(path) = log_file.as_ref().filter(|p| p.as_os_str() != "-" && !p.exists()) {
+        eprintln!("Error: Log file not found at '{}'", path.display());
         return Ok(());
     }
     if !args.xid_errors_csv.exists() {
         eprintln!("Warning: XID errors CSV not found at '{}'. Descriptions will be unavailable.", args.xid_errors_csv.display());
     }
 
-    let log_content = fs::read_to_string(&args.log_file)?.replace('\r', "");
+    let mut input = match &log_file {
+        Some(path) => input::read(path)?,
+        None => input::Input { content: String::new(), sources: Vec::new(), report_len: 0 },
+    };
+    for (path, title) in artifacts {
+        let Some(path) = path else { continue };
+        let artifact = input::read(path)?;
+        input::append_section(&mut input.content, &format!("{} ({})", title, path.display()), &artifact.content);
+        input.sources.push(format!("{}: {}", title, path.display()));
+    }
+    let quality = quality::DataQuality::assess(&input.content[..input.report_len]);
+    let mut log_content = input.content.replace('\r', "");
+    if args.redact {
+        let mut redactor = redact::Redactor::default();
+        redactor.redact(&log_content);
+        output::redact_with(redactor);
+    }
+    let all_boots = boot::BootMap::parse(&log_content);
+    if args.current_boot {
+        log_content = all_boots.current_boot_only(&log_content);
+    }
+    // Checks index the log as analyzed, so --current-boot needs its own map
+    let boots = if args.current_boot { boot::BootMap::parse(&log_content) } else { all_boots.clone() };
 
     println!("\n==================================================");
     println!(" NVIDIA Bug Report Log Summary - Wharton Wang v.0.2.0");
     println!("==================================================\n");
+    if log_file.as_ref().is_none_or(|path| input.sources != [path.display().to_string()]) {
+        println!("Sources: {}", input.sources.join(", "));
+    }
+
+    let platform = platform::detect(&log_content);
+    let coverage = input::Coverage::detect(&log_content);
+    let remedies = remediation::KnowledgeBase::load(args.remediation_csv.as_deref());
+    quality::print_data_quality(&quality, &log_content, &coverage);
 
-    print_system_summary(&log_content);
+    print_system_summary(&log_content, &platform);
+    if all_boots.previous_boots() > 0 {
+        let ids: Vec<&str> = all_boots.boots.iter().filter(|b| b.offset < 0).filter_map(|b| b.id.as_deref()).collect();
+        println!("  Previous boots in journal/dmesg: {}{}{}", all_boots.previous_boots(),
+            if ids.is_empty() { String::new() } else { format!(" ({})", ids.join(", ")) },
+            if args.current_boot { ", excluded by --current-boot" } else { "" });
+    }
     print_gpu_summary(&log_content);
+    let firmware_mismatches = evaluate(&coverage, "GPU Firmware Mismatches", &[Smi], || check_firmware_consistency(&log_content));
+    let missing_gpus = evaluate(&coverage, "Missing GPUs", &[Lspci], || check_gpu_count(&log_content, args.expected_gpus.or(platform.expected_gpus())));
+    let known_issues = known_issues::check_known_issues(&log_content, &platform, &known_issues::load(args.known_issues_csv.as_deref()));
     
     // Detailed checks
-    let version_conflicts = check_version_conflicts(&log_content);
-    let xid_errors = check_xid_errors(&log_content, &args.xid_errors_csv);
-    let nvlink_errors = check_nvlink_errors(&log_content);
-    let temp_status = check_gpu_temperatures(&log_content);
-    let ecc_errors = check_ecc_errors(&log_content);
-    let nic_pcie_issues = check_nic_pcie_status(&log_content);
-    let power_status = check_gpu_power_performance(&log_content);
+    let version_conflicts = evaluate(&coverage, "Driver/Fabric Manager Conflicts", &[KernelLog], || check_version_conflicts(&log_content, &boots, &platform, &remedies));
+    let driver_mismatches = driver::check_driver_stack(&log_content);
+    let module_issues = driver::check_kernel_modules(&log_content);
+    let cmdline_issues = cmdline::check_kernel_cmdline(&log_content, &platform);
+    let xid_errors = evaluate(&coverage, "Xid Errors", &[KernelLog], || check_xid_errors(&log_content, &boots, &args.xid_errors_csv));
+    let nvlink_errors = evaluate(&coverage, "NVLink Errors", &[Smi], || check_nvlink_errors(&log_content, &platform));
+    let temp_status = evaluate(&coverage, "GPU Temperature Issues", &[Smi], || check_gpu_temperatures(&log_content));
+    let ecc_errors = evaluate(&coverage, "ECC Memory Errors", &[Smi], || check_ecc_errors(&log_content));
+    let row_remapping = evaluate(&coverage, "Row Remapping/Retired Pages", &[Smi], || check_row_remapping(&log_content));
+    let nic_pcie_issues = evaluate(&coverage, "NIC PCIe Issues", &[KernelLog], || check_nic_pcie_status(&log_content));
+    let aer_errors = evaluate(&coverage, "PCIe AER Errors", &[KernelLog], || pcie::check_pcie_aer(&log_content, &boots));
+    let iommu_faults = evaluate(&coverage, "IOMMU/DMAR Faults", &[KernelLog], || pcie::check_iommu_faults(&log_content, &boots));
+    let power_status = evaluate(&coverage, "GPU Power/Performance Issues", &[Smi], || check_gpu_power_performance(&log_content));
+    let clock_slowdown = evaluate(&coverage, "Clock Slowdown (Thermal/HW)", &[Smi], || check_clock_event_reasons(&log_content, &boots));
     
     // Simple status checks
-    let thermal_slowdown = check_thermal_slowdown(&log_content);
-    let segfaults = check_segfaults(&log_content);
-    let cpu_throttle = check_cpu_throttle(&log_content);
-    let hardware_errors = check_hardware_errors(&log_content);
-    let fallen_off_bus = check_fallen_off_bus(&log_content);
-    let rminit_failed = check_rminit_failed(&log_content);
-    let power_state_refused = check_power_state_refused(&log_content);
-    let bad_cpu = check_bad_cpu(&log_content);
+    let segfaults = evaluate(&coverage, "Segfaults", &[KernelLog], || check_segfaults(&log_content, &boots));
+    let cpu_throttle = evaluate(&coverage, "CPU Throttling", &[KernelLog], || check_cpu_throttle(&log_content, &boots));
+    let hardware_errors = evaluate(&coverage, "Hardware Errors", &[KernelLog], || mce::check_hardware_errors(&log_content, &boots));
+    let fallen_off_bus = evaluate(&coverage, "GPU Fallen Off Bus", &[KernelLog], || check_fallen_off_bus(&log_content, &boots));
+    let rminit_failed = evaluate(&coverage, "RmInit Failures", &[KernelLog], || check_rminit_failed(&log_content, &boots));
+    let power_state_refused = evaluate(&coverage, "Power State Refused", &[KernelLog], || check_power_state_refused(&log_content, &boots));
+    let bad_cpu = evaluate(&coverage, "Bad CPU Errors", &[KernelLog], || check_bad_cpu(&log_content, &boots, &remedies));
+
+    let root_causes = correlate::analyze(&log_content, &boots);
+    correlate::print_root_causes(&root_causes);
+
+    if args.timeline || args.timeline_window.is_some() {
+        timeline::print_timeline(&log_content, &boots, args.timeline_window);
+    }
 
     // Final Summary
     println!("\n==================================================");
     println!("     FINAL SUMMARY");
     println!("==================================================\n");
     
-    print_summary_item("Driver/Fabric Manager Conflicts", version_conflicts);
-    print_summary_item("Xid Errors", xid_errors);
-    print_summary_item("NVLink Errors", nvlink_errors);
-    print_summary_item("GPU Temperature Issues", temp_status);
-    print_summary_item("ECC Memory Errors", ecc_errors);
-    print_summary_item("NIC PCIe Issues", nic_pcie_issues);
-    print_summary_item("GPU Power/Performance Issues", power_status);
-    print_summary_item("Thermal Slowdown", thermal_slowdown);
-    print_summary_item("Segfaults", segfaults);
-    print_summary_item("CPU Throttling", cpu_throttle);
-    print_summary_item("Hardware Errors", hardware_errors);
-    print_summary_item("GPU Fallen Off Bus", fallen_off_bus);
-    print_summary_item("RmInit Failures", rminit_failed);
-    print_summary_item("Power State Refused", power_state_refused);
-    print_summary_item("Bad CPU Errors", bad_cpu);
+    let results = [
+        ("GPU Firmware Mismatches", firmware_mismatches),
+        ("Missing GPUs", missing_gpus),
+        ("Known Issues", known_issues),
+        ("Driver/Fabric Manager Conflicts", version_conflicts),
+        ("Driver Stack Mismatches", driver_mismatches),
+        ("Kernel Module Issues", module_issues),
+        ("Kernel Command Line Issues", cmdline_issues),
+        ("Xid Errors", xid_errors),
+        ("NVLink Errors", nvlink_errors),
+        ("GPU Temperature Issues", temp_status),
+        ("ECC Memory Errors", ecc_errors),
+        ("Row Remapping/Retired Pages", row_remapping),
+        ("NIC PCIe Issues", nic_pcie_issues),
+        ("PCIe AER Errors", aer_errors),
+        ("IOMMU/DMAR Faults", iommu_faults),
+        ("GPU Power/Performance Issues", power_status),
+        ("Clock Slowdown (Thermal/HW)", clock_slowdown),
+        ("Segfaults", segfaults),
+        ("CPU Throttling", cpu_throttle),
+        ("Hardware Errors", hardware_errors),
+        ("GPU Fallen Off Bus", fallen_off_bus),
+        ("RmInit Failures", rminit_failed),
+        ("Power State Refused", power_state_refused),
+        ("Bad CPU Errors", bad_cpu),
+    ];
+    for (name, result) in &results {
+        print_summary_item(name, result);
+    }
+    let not_evaluated = results.iter().filter(|(_, result)| matches!(result, CheckResult::NotEvaluated(_))).count();
+    if not_evaluated > 0 {
+        println!("\n  {} check(s) not evaluated: missing data is not evidence of a healthy system", not_evaluated);
+    }
+    let not_applicable: Vec<&str> = results.iter().filter(|(_, result)| matches!(result, CheckResult::NotApplicable(_))).map(|(name, _)| *name).collect();
+    if !not_applicable.is_empty() {
+        println!("  {} check(s) skipped as not applicable to a {} platform: {}", not_applicable.len(), platform.form_factor, not_applicable.join(", "));
+    }
+    if quality.truncated() {
+        println!("  Report is truncated: findings only cover the sections collected before nvidia-bug-report.sh stopped");
+    }
+
+    if let Some(top) = root_causes.first() {
+        println!("\n  Probable root cause: {} ({}): {} -> {}", top.subject(), boot::label(top.boot), top.cause, top.action);
+    }
+
+    print_remediation(&log_content, &remedies, &results);
 
     println!("\n==================================================");
     println!("     End of Report");
@@ -100,13 +284,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn print_system_summary(log: &str) {
+fn print_system_summary(log: &str, platform: &platform::Platform) {
     if let Some(cap) = RE_DATE.captures(log) {
         println!("Log from: {}", &cap[1]);
     }
 
     if let Some(cap) = RE_NVIDIA_VERSION.captures(log) {
-        println!("NVIDIA Driver Version: {}", &cap[1]);
+        println!("NVIDIA Driver Version: {}", cap[1].trim());
+    }
+    if let Some(version) = driver::kernel_module_version(log) {
+        println!("NVIDIA Kernel Module Version: {}", version);
     }
 
     if let Some(cap) = RE_CHASSIS_DMI.captures(log) {
@@ -125,6 +312,8 @@ fn print_system_summary(log: &str) {
          println!("BaseBoard info: {}", prod);
     }
 
+    println!("Platform: {}", platform.description());
+
     if let Some(bios_block) = RE_BIOS_INFO.captures(log) {
          let ver = extract_field(&bios_block[1], "Version:");
          let date = extract_field(&bios_block[1], "Release Date:");
@@ -136,8 +325,8 @@ fn print_system_summary(log: &str) {
         println!("  CPUs # threads: {} of CPU: {}", count, model);
     }
 
-    if let Some(cap) = RE_CMDLINE.captures(log) {
-        println!("  BOOT Line: {}", &cap[1]);
+    if let Some(cmdline) = cmdline::find(log) {
+        println!("  BOOT Line: {}", cmdline.raw);
     }
 
     println!("  Memory:");
@@ -167,37 +356,43 @@ fn print_system_summary(log: &str) {
 }
 
 fn print_gpu_summary(log: &str) {
-    println!("Summary of PCI Addresses and GPUs");
-    
-    // Try to parse nvidia-smi format first (GPU 00000000:19:00.0)
-    let smi_gpus: Vec<_> = RE_GPU_SMI_BLOCK.captures_iter(log).collect();
-    
-    if !smi_gpus.is_empty() {
-        // Parse nvidia-smi output format
-        let lines: Vec<&str> = log.lines().collect();
-        for cap in smi_gpus {
-            let bus_addr = &cap[1];
-            let gpu_line_idx = lines.iter().position(|&l| l.contains(&format!("GPU {}", bus_addr)));
-            
-            if let Some(idx) = gpu_line_idx {
-                // Look for GPU UUID in the next ~20 lines
-                let mut uuid = "N/A";
-                for i in idx..std::cmp::min(idx + 30, lines.len()) {
-                    if let Some(uuid_cap) = RE_GPU_UUID.captures(lines[i]) {
-                        uuid = uuid_cap.get(1).unwrap().as_str();
-                        break;
-                    }
-                }
-                println!("{} {}", bus_addr, uuid);
-            }
-        }
-    } else {
-        // Fallback to old format (GPU 0:, GPU 1:, etc.)
-        for block in RE_GPU_BLOCK.captures_iter(log) {
-            let uuid = RE_GPU_UUID.captures(&block[1]).map_or("N/A", |c| c.get(1).unwrap().as_str());
-            let bus = RE_BUS_LOCATION.captures(&block[1]).map_or("N/A", |c| c.get(1).unwrap().as_str());
-            println!("{} {}", bus, uuid);
+    let inventory: Vec<_> = smi::parse_gpus(log).iter().map(smi::GpuInventory::from_gpu).collect();
+
+    if !inventory.is_empty() {
+        println!("GPU Inventory");
+        let headers = ["Idx", "Bus ID", "Product Name", "Serial Number", "UUID", "Board Part Number",
+            "VBIOS", "InfoROM", "GSP Firmware", "Memory", "Module"];
+        let rows: Vec<Vec<String>> = inventory.iter().map(|gpu| vec![
+            gpu.index.to_string(),
+            gpu.bus_id.clone(),
+            gpu.product_name.clone(),
+            gpu.serial_number.clone(),
+            gpu.uuid.clone(),
+            gpu.board_part_number.clone(),
+            gpu.vbios_version.clone(),
+            format!("{} (OEM {}, ECC {}, PWR {})", gpu.inforom_image, gpu.inforom_oem, gpu.inforom_ecc, gpu.inforom_power),
+            gpu.gsp_firmware.clone(),
+            gpu.memory_total.clone(),
+            gpu.module_id.clone(),
+        ]).collect();
+        print_table(&headers, &rows);
+        println!();
+
+        println!("GPUs:");
+        let model_counts = count_occurrences(inventory.iter().map(|gpu| gpu.product_name.as_str()));
+        for (model, count) in model_counts {
+            println!("    {} {}", count, model);
         }
+        println!();
+        return;
+    }
+
+    // Fallback to old format (GPU 0:, GPU 1:, etc.)
+    println!("Summary of PCI Addresses and GPUs");
+    for block in RE_GPU_BLOCK.captures_iter(log) {
+        let uuid = RE_GPU_UUID.captures(&block[1]).map_or("N/A", |c| c.get(1).unwrap().as_str());
+        let bus = RE_BUS_LOCATION.captures(&block[1]).map_or("N/A", |c| c.get(1).unwrap().as_str());
+        println!("{} {}", bus, uuid);
     }
     println!();
 
@@ -214,9 +409,130 @@ fn print_gpu_summary(log: &str) {
     println!();
 }
 
-fn check_pattern(log: &str, pattern: &str, header: &str, not_found_msg: &str, advice: Option<&str>) -> usize {
+fn check_firmware_consistency(log: &str) -> CheckResult {
+    let inventory: Vec<_> = smi::parse_gpus(log).iter().map(smi::GpuInventory::from_gpu).collect();
+    if inventory.is_empty() {
+        return CheckResult::NotEvaluated("no GPUs in nvidia-smi -q output".to_string());
+    }
+    if inventory.len() < 2 {
+        return CheckResult::Ok;
+    }
+
+    let fields = [
+        ("VBIOS", inventory.iter().map(|gpu| gpu.vbios_version.as_str()).collect::<Vec<_>>()),
+        ("InfoROM image", inventory.iter().map(|gpu| gpu.inforom_image.as_str()).collect()),
+        ("GSP firmware", inventory.iter().map(|gpu| gpu.gsp_firmware.as_str()).collect()),
+    ];
+
+    let mut outliers = Vec::new();
+    for (label, versions) in &fields {
+        // Different GPU models legitimately run different firmware
+        let mut by_model: HashMap<&str, Vec<(&smi::GpuInventory, &str)>> = HashMap::new();
+        // GPUs that don't report a version (N/A before GSP, or an unreadable InfoROM) are not outliers
+        for (gpu, version) in inventory.iter().zip(versions).filter(|(_, version)| !version.is_empty() && **version != "N/A") {
+            by_model.entry(gpu.product_name.as_str()).or_default().push((gpu, version));
+        }
+        for gpus in by_model.values() {
+            let counts = count_occurrences(gpus.iter().map(|(_, version)| version));
+            if counts.len() < 2 {
+                continue;
+            }
+            let (common, common_count) = counts.iter().max_by_key(|(version, count)| (**count, (*version).clone())).unwrap();
+            for (gpu, version) in gpus.iter().filter(|(_, version)| version != common) {
+                outliers.push(format!("GPU {} ({}) {} {} differs, {} GPU(s) report {}",
+                    gpu.index, gpu.bus_id, label, version, common_count, common));
+            }
+        }
+    }
+
+    if outliers.is_empty() {
+        println!("Firmware versions (VBIOS, InfoROM, GSP) are consistent across {} GPUs\n", inventory.len());
+    } else {
+        println!("** Firmware Version Mismatches: {}", outliers.len());
+        for outlier in &outliers {
+            println!("   {}", outlier);
+        }
+        println!("   Mixed firmware is common after partial board replacements, update all GPUs to the same version.\n");
+    }
+    outliers.len().into()
+}
+
+/// A GPU that never initialized produces no "fallen off the bus" message, it
+/// is simply absent from nvidia-smi. Compare nvidia-smi against lspci and the
+/// expected count to catch it.
+fn check_gpu_count(log: &str, expected: Option<usize>) -> usize {
+    let smi_bus_ids: Vec<String> = smi::parse_gpus(log).iter().map(|gpu| gpu.short_bus_id()).collect();
+    let mut pci_bus_ids: Vec<String> = RE_LSPCI_NVIDIA_GPU.captures_iter(log).map(|cap| cap[1].to_lowercase()).collect();
+    pci_bus_ids.sort();
+    pci_bus_ids.dedup();
+
+    println!("GPU count: {} in nvidia-smi, {} NVIDIA display controller(s) in lspci{}",
+        smi_bus_ids.len(), pci_bus_ids.len(),
+        expected.map_or(String::new(), |n| format!(", {} expected", n)));
+
+    let mut missing = 0;
+    // nvidia-smi that ran but lists no GPUs is the worst case, only skip this without its output
+    if !smi_bus_ids.is_empty() || RE_SMI_RAN.is_match(log) {
+        for bus_id in pci_bus_ids.iter().filter(|id| !smi_bus_ids.contains(id)) {
+            println!("** GPU {} is enumerated on PCI but missing from nvidia-smi", bus_id);
+            missing += 1;
+        }
+    }
+    if let Some(expected) = expected {
+        let seen = smi_bus_ids.len().max(pci_bus_ids.len());
+        if seen < expected {
+            println!("** {} GPU(s) missing from PCI enumeration entirely, check seating, power and BIOS PCIe settings", expected - seen);
+            missing += expected - seen;
+        }
+    }
+    if missing > 0 {
+        println!("   See also the 'fallen off the bus' and RmInitAdapter checks below.");
+    }
+    println!();
+    missing
+}
+
+/// Outcome of a check. A check that had no data to look at is not evaluated,
+/// which must not be mistaken for a healthy system; one that does not apply to
+/// this platform (fabric manager on PCIe) is not applicable.
+#[derive(Debug, Clone, PartialEq)]
+enum CheckResult {
+    Ok,
+    Issues(usize),
+    NotEvaluated(String),
+    NotApplicable(String),
+}
+
+impl CheckResult {
+    fn issues(&self) -> usize {
+        match self {
+            CheckResult::Issues(count) => *count,
+            _ => 0,
+        }
+    }
+}
+
+impl From<usize> for CheckResult {
+    fn from(count: usize) -> CheckResult {
+        if count > 0 { CheckResult::Issues(count) } else { CheckResult::Ok }
+    }
+}
+
+/// Runs a check when the input has any of the data it needs, otherwise says
+/// it was not evaluated rather than reporting that nothing was found.
+fn evaluate<R: Into<CheckResult>>(coverage: &input::Coverage, name: &str, needs: &[input::Data], check: impl FnOnce() -> R) -> CheckResult {
+    if needs.iter().any(|data| coverage.has(*data)) {
+        return check().into();
+    }
+    let labels: Vec<&str> = needs.iter().map(|data| data.label()).collect();
+    let reason = format!("no {} in the input", labels.join(" or "));
+    println!("\n{}: not evaluated, {}", name, reason);
+    CheckResult::NotEvaluated(reason)
+}
+
+fn check_pattern(log: &str, boots: &boot::BootMap, pattern: &str, header: &str, not_found_msg: &str, advice: Option<&str>) -> usize {
     let re = Regex::new(pattern).unwrap();
-    let matches: Vec<_> = re.find_iter(log).map(|m| m.as_str().to_string()).collect();
+    let matches = boots.unique(log, re.find_iter(log).map(|m| (m.start(), m)));
     
     if matches.is_empty() {
         println!("{}", not_found_msg);
@@ -227,22 +543,38 @@ fn check_pattern(log: &str, pattern: &str, header: &str, not_found_msg: &str, ad
             println!("{}", adv);
         }
         
-        let counts = count_occurrences(matches.iter().map(|s| s.trim()));
+        let counts = count_occurrences(matches.iter().map(|m| m.as_str().trim()));
         for (line, count) in counts {
              println!("    ({}x) {}", count, line);
         }
+        boot::print_breakdown(boots, matches.iter().map(|m| m.start()));
         matches.len()
     }
 }
 
-fn check_version_conflicts(log: &str) -> usize {
+fn check_version_conflicts(log: &str, boots: &boot::BootMap, platform: &platform::Platform, remedies: &remediation::KnowledgeBase) -> CheckResult {
+    if !platform.needs_fabric_manager() {
+        println!("Driver/Fabric Manager conflict check skipped: fabric manager is only used on SXM systems, platform is {}", platform.form_factor);
+        return CheckResult::NotApplicable(format!("fabric manager is only used on SXM systems, platform is {}", platform.form_factor));
+    }
     let pattern = r"Please update with matching NVIDIA driver";
-    let advice = "     ** This is important for any SXM chassis **\n     Check with:\n        'nvidia-smi topo -m'\n         python -c \"import torch ; print('Is available: ', torch.cuda.is_available())\"";
-    check_pattern(log, pattern, "GPU Driver and Fabric Manager Conflicts", "No version conflicts found", Some(advice))
+    let advice = remedies.check("Driver/Fabric Manager Conflicts").map(|r| format!("   {}", r.action));
+    let conflicts = check_pattern(log, boots, pattern, "GPU Driver and Fabric Manager Conflicts", "No version conflicts found", advice.as_deref());
+    // Without fabric manager log lines, silence on a platform that may not run it proves nothing
+    if conflicts == 0 && platform.form_factor == platform::FormFactor::Unknown {
+        println!("   Platform form factor unknown, so fabric manager may not be in use");
+        return CheckResult::NotEvaluated("platform form factor unknown".to_string());
+    }
+    conflicts.into()
+}
+
+/// Xid messages, counting an event once when both the dmesg and the journal section log it.
+fn xid_events<'a>(log: &'a str, boots: &boot::BootMap) -> Vec<regex::Captures<'a>> {
+    boots.unique(log, RE_XID.captures_iter(log).map(|cap| (cap.get(0).unwrap().start(), cap)))
 }
 
-fn check_xid_errors(log: &str, csv_path: &Path) -> usize {
-    let matches: Vec<_> = RE_XID.captures_iter(log).collect();
+fn check_xid_errors(log: &str, boots: &boot::BootMap, csv_path: &Path) -> usize {
+    let matches = xid_events(log, boots);
 
     if matches.is_empty() {
         println!("No Xid errors found");
@@ -256,6 +588,7 @@ fn check_xid_errors(log: &str, csv_path: &Path) -> usize {
     for (line, count) in counts {
         println!("    ({}x) {}", count, line);
     }
+    boot::print_breakdown(boots, matches.iter().map(|cap| cap.get(0).unwrap().start()));
     
     println!("\nSummary of error descriptions:");
     let xid_map = load_xid_descriptions(csv_path);
@@ -275,49 +608,138 @@ fn check_xid_errors(log: &str, csv_path: &Path) -> usize {
     matches.len()
 }
 
-fn check_thermal_slowdown(log: &str) -> usize {
-    check_pattern(log, r"(?m).*(?:SW|HW) Thermal Slowdown.*: Active$", "Thermal Slow down", "No thermal slowdown messages found", None)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ClockReasonKind {
+    Benign,
+    Software,
+    Hardware,
 }
 
-fn check_segfaults(log: &str) -> usize {
-    check_pattern(log, r"(?m).*segfault.*$", "Segfaults", "No segfaults found", None)
+/// Classifies an entry of the "Clocks Event Reasons" block. Idle and
+/// configuration-driven reasons are expected; power capping is the driver
+/// doing its job; thermal and hardware slowdowns are real problems.
+fn clock_reason_kind(reason: &str) -> ClockReasonKind {
+    match reason {
+        "SW Power Cap" => ClockReasonKind::Software,
+        "SW Thermal Slowdown" | "HW Slowdown" | "HW Thermal Slowdown" | "HW Power Brake Slowdown" => ClockReasonKind::Hardware,
+        _ => ClockReasonKind::Benign,
+    }
 }
 
-fn check_cpu_throttle(log: &str) -> usize {
-    check_pattern(log, r"(?m).*cpu clock throttled.*$", "CPU throttling", "No CPU throttling", None)
+fn check_clock_event_reasons(log: &str, boots: &boot::BootMap) -> usize {
+    println!("\n==================================================");
+    println!("     Clock Event Reasons Check");
+    println!("==================================================\n");
+
+    let gpus = smi::parse_gpus(log);
+    let mut evaluated = 0;
+    let mut throttled = 0;
+    let mut all_active = Vec::new();
+
+    for gpu in &gpus {
+        // Renamed from "Clocks Throttle Reasons" in driver 535
+        let Some(reasons) = gpu.root.child("Clocks Event Reasons").or_else(|| gpu.root.child("Clocks Throttle Reasons")) else {
+            continue;
+        };
+        evaluated += 1;
+
+        let mut active = Vec::new();
+        let mut pending = reasons.children.iter().collect::<Vec<_>>();
+        while let Some(reason) = pending.pop() {
+            pending.extend(reason.children.iter());
+            if reason.value.as_deref() == Some("Active") {
+                active.push((clock_reason_kind(&reason.key), reason.key.as_str()));
+            }
+        }
+        if active.is_empty() {
+            continue;
+        }
+        active.sort();
+        all_active.extend(active.iter().map(|(_, name)| name.to_string()));
+
+        let worst = active.iter().map(|(kind, _)| *kind).max().unwrap();
+        let names: Vec<_> = active.iter().map(|(_, name)| *name).collect();
+        match worst {
+            ClockReasonKind::Hardware => {
+                throttled += 1;
+                println!("** GPU {} ({}) clocked down by hardware/thermal limits: {}", gpu.index, gpu.bus_id, names.join(", "));
+            }
+            ClockReasonKind::Software => {
+                println!("   GPU {} ({}) clocked down by software power cap: {}", gpu.index, gpu.bus_id, names.join(", "));
+            }
+            ClockReasonKind::Benign => {
+                println!("   GPU {} ({}) benign: {}", gpu.index, gpu.bus_id, names.join(", "));
+            }
+        }
+    }
+
+    if evaluated == 0 {
+        return check_pattern(log, boots, r"(?m).*(?:SW|HW) Thermal Slowdown.*: Active$", "Thermal Slow down", "No thermal slowdown messages found", None);
+    }
+
+    if throttled > 0 {
+        println!("\n** {} GPU(s) held back by thermal or hardware slowdown", throttled);
+        if all_active.iter().any(|r| r == "HW Power Brake Slowdown") {
+            println!("   HW Power Brake Slowdown is asserted externally, check PSUs and the chassis power brake signal.");
+        }
+        if all_active.iter().any(|r| r.ends_with("Thermal Slowdown")) {
+            println!("   Thermal slowdown points to cooling, check fans, airflow and inlet temperature.");
+        }
+    } else {
+        println!("No hardware or thermal clock slowdowns on {} GPU(s)", evaluated);
+    }
+
+    throttled
 }
 
-fn check_hardware_errors(log: &str) -> usize {
-    let advice = "  To find specific errors:\n      `grep -E 'Hardware Error' <logfile>` on Linux, or search in a text editor on Windows.";
-    check_pattern(log, r"Hardware Error", "Hardware Errors", "No Hardware Errors found", Some(advice))
+fn check_segfaults(log: &str, boots: &boot::BootMap) -> usize {
+    check_pattern(log, boots, r"(?m).*segfault.*$", "Segfaults", "No segfaults found", None)
 }
 
-fn check_fallen_off_bus(log: &str) -> usize {
-    check_pattern(log, r"(?m)kernel: NVRM:.*GPU has fallen off the bus.*$", "Fallen off the bus Errors", "No 'fallen off the bus' errors", None)
+fn check_cpu_throttle(log: &str, boots: &boot::BootMap) -> usize {
+    check_pattern(log, boots, r"(?m).*cpu clock throttled.*$", "CPU throttling", "No CPU throttling", None)
 }
 
-fn check_rminit_failed(log: &str) -> usize {
-    check_pattern(log, r"(?m).*(?:RmInitAdapter failed|rm_init_adapter failed).*", "GPU RmInitAdapter Failed", "No 'RmInit failures'", None)
+fn check_fallen_off_bus(log: &str, boots: &boot::BootMap) -> usize {
+    check_pattern(log, boots, r"(?m)NVRM:.*GPU has fallen off the bus.*$", "Fallen off the bus Errors", "No 'fallen off the bus' errors", None)
 }
 
-fn check_power_state_refused(log: &str) -> usize {
-     check_pattern(log, r"kernel: nvidia-gpu.*Refused to change power state,", "Power State Change Refused", "No 'Refused to change power state' messages", None)
+fn check_rminit_failed(log: &str, boots: &boot::BootMap) -> usize {
+    check_pattern(log, boots, r"(?m).*(?:RmInitAdapter failed|rm_init_adapter failed).*", "GPU RmInitAdapter Failed", "No 'RmInit failures'", None)
 }
 
-fn check_bad_cpu(log: &str) -> usize {
-    let advice = "Commonly due to only 255 of 256+ threads seen.\nQuick fix: disable SMT in BIOS.\nReal fix depends on Motherboard/BIOS (e.g., enabling X2APIC and IOMMU).";
-    check_pattern(log, r"bad cpu", "Bad CPU Error", "No 'bad cpu' Errors found", Some(advice))
+fn check_power_state_refused(log: &str, boots: &boot::BootMap) -> usize {
+     check_pattern(log, boots, r"nvidia-gpu.*Refused to change power state,", "Power State Change Refused", "No 'Refused to change power state' messages", None)
 }
 
-fn check_nvlink_errors(log: &str) -> usize {
+fn check_bad_cpu(log: &str, boots: &boot::BootMap, remedies: &remediation::KnowledgeBase) -> usize {
+    let advice = remedies.check("Bad CPU Errors").map(|r| format!("   {}", r.action));
+    check_pattern(log, boots, r"bad cpu", "Bad CPU Error", "No 'bad cpu' Errors found", advice.as_deref())
+}
+
+fn check_nvlink_errors(log: &str, platform: &platform::Platform) -> CheckResult {
     println!("\n==================================================");
     println!("     NVLink Status Check");
     println!("==================================================\n");
+
+    if !platform.has_nvlink(log) && platform.form_factor == platform::FormFactor::Unknown {
+        println!("NVLink check not evaluated: platform form factor unknown and no NVLink output");
+        return CheckResult::NotEvaluated("platform form factor unknown, no NVLink output".to_string());
+    }
+    if !platform.has_nvlink(log) {
+        println!("NVLink check skipped: {} platform without NVLink", platform.form_factor);
+        return CheckResult::NotApplicable(format!("{} platform without NVLink", platform.form_factor));
+    }
     
     let replay_re = Regex::new(r"Link \d+: Replay Errors: (\d+)").unwrap();
     let recovery_re = Regex::new(r"Link \d+: Recovery Errors: (\d+)").unwrap();
     let crc_re = Regex::new(r"Link \d+: CRC Errors: (\d+)").unwrap();
     
+    if !replay_re.is_match(log) && !recovery_re.is_match(log) && !crc_re.is_match(log) {
+        println!("No NVLink error counters found (nvidia-smi nvlink -e)");
+        return CheckResult::NotEvaluated("no NVLink error counters in the input".to_string());
+    }
+
     let mut total_replay = 0;
     let mut total_recovery = 0;
     let mut total_crc = 0;
@@ -350,93 +772,289 @@ fn check_nvlink_errors(log: &str) -> usize {
         println!("No NVLink errors detected - All links healthy");
     }
     
-    total_errors
+    total_errors.into()
 }
 
-fn check_gpu_temperatures(log: &str) -> usize {
+/// Used only when a GPU does not report its own slowdown threshold.
+const FALLBACK_SLOWDOWN_TEMP: f64 = 85.0;
+const TEMP_WARNING_MARGIN: f64 = 5.0;
+const TEMP_CAUTION_MARGIN: f64 = 10.0;
+
+fn check_gpu_temperatures(log: &str) -> CheckResult {
     println!("\n==================================================");
     println!("     GPU Temperature Check");
     println!("==================================================\n");
-    
-    let temp_re = Regex::new(r"GPU Current Temp\s*:\s*(\d+)\s*C").unwrap();
-    let mut temps = Vec::new();
-    
-    for cap in temp_re.captures_iter(log) {
-        if let Ok(temp) = cap[1].parse::<i32>() {
-            temps.push(temp);
-        }
-    }
-    
+
+    let gpus = smi::parse_gpus(log);
+    let temps: Vec<_> = gpus.iter()
+        .filter_map(|gpu| gpu.root.child("Temperature").map(|t| (gpu, t)))
+        .filter(|(_, t)| t.number(&["GPU Current Temp"]).is_some())
+        .collect();
+
     if temps.is_empty() {
         println!("No temperature data found");
-        return 0;
+        return CheckResult::NotEvaluated("no GPU temperatures in nvidia-smi output".to_string());
     }
-    
-    let max_temp = temps.iter().max().unwrap();
-    let min_temp = temps.iter().min().unwrap();
-    let avg_temp = temps.iter().sum::<i32>() / temps.len() as i32;
-    
-    println!("GPU Temperatures:");
-    println!("   Min: {}°C, Max: {}°C, Avg: {}°C", min_temp, max_temp, avg_temp);
-    
-    let issues = if *max_temp > 85 {
-        println!("   ** WARNING: High temperature detected (>85°C)!");
-        temps.iter().filter(|&&t| t > 85).count()
-    } else if *max_temp > 75 {
-        println!("   ** CAUTION: Elevated temperature (>75°C)");
-        temps.iter().filter(|&&t| t > 75).count()
+
+    println!("   {:<4} {:<18} {:>6} {:>6} {:>9} {:>9} {:>9} {:>7}", "GPU", "Bus ID", "GPU", "Mem", "Slowdown", "Shutdown", "Max Op", "Margin");
+    let fmt = |v: Option<f64>| v.map_or("N/A".to_string(), |v| format!("{:.0}°C", v));
+    let mut issues = Vec::new();
+    let mut cautions = Vec::new();
+    let mut assumed_threshold = false;
+
+    for (gpu, t) in &temps {
+        let current = t.number(&["GPU Current Temp"]).unwrap_or_default();
+        let memory = t.number(&["Memory Current Temp"]);
+        let slowdown = t.number(&["GPU Slowdown Temp"]);
+        let shutdown = t.number(&["GPU Shutdown Temp"]);
+        let max_op = t.number(&["GPU Max Operating Temp"]);
+        let memory_max = t.number(&["Memory Max Operating Temp"]);
+
+        // Newer drivers drop the absolute thresholds and report "GPU T.Limit Temp",
+        // the current headroom, with the slowdown point relative to it.
+        let (margin, threshold_known) = match (slowdown, t.number(&["GPU T.Limit Temp"])) {
+            (Some(slowdown), _) => (slowdown - current, true),
+            (None, Some(tlimit)) => (tlimit - t.number(&["GPU Slowdown T.Limit Temp"]).unwrap_or(0.0), true),
+            (None, None) => (FALLBACK_SLOWDOWN_TEMP - current, false),
+        };
+        assumed_threshold |= !threshold_known;
+
+        let name = format!("GPU {} ({})", gpu.index, gpu.bus_id);
+        if margin < TEMP_WARNING_MARGIN {
+            issues.push(format!("** WARNING: {} is {:.0}°C, {:.0}°C from its slowdown threshold", name, current, margin));
+        } else if margin < TEMP_CAUTION_MARGIN {
+            cautions.push(format!("** CAUTION: {} is {:.0}°C, {:.0}°C from its slowdown threshold", name, current, margin));
+        }
+        if let (Some(memory), Some(memory_max)) = (memory, memory_max) {
+            if memory >= memory_max {
+                issues.push(format!("** WARNING: {} memory is {:.0}°C, at or above its max operating temp {:.0}°C", name, memory, memory_max));
+            }
+        }
+
+        println!("   {:<4} {:<18} {:>6} {:>6} {:>9} {:>9} {:>9} {:>7}",
+            gpu.index, gpu.bus_id, fmt(Some(current)), fmt(memory), fmt(slowdown), fmt(shutdown), fmt(max_op),
+            if threshold_known { format!("{:.0}°C", margin) } else { format!("{:.0}°C*", margin) });
+    }
+
+    if assumed_threshold {
+        println!("   * no slowdown threshold reported, margin assumes {:.0}°C", FALLBACK_SLOWDOWN_TEMP);
+    }
+
+    if issues.is_empty() && cautions.is_empty() {
+        println!("\n   Temperatures are within each GPU's limits");
     } else {
-        println!("   Temperatures are within normal range");
-        0
-    };
-    
-    issues
+        println!();
+        for line in issues.iter().chain(&cautions) {
+            println!("   {}", line);
+        }
+    }
+
+    issues.len().into()
+}
+
+/// ECC error counters for one GPU, split the way `nvidia-smi -q` reports them.
+#[derive(Debug, Default, Clone, Copy)]
+struct EccCounts {
+    sram_correctable: u64,
+    sram_uncorrectable: u64,
+    dram_correctable: u64,
+    dram_uncorrectable: u64,
+}
+
+impl EccCounts {
+    /// Parses a `Volatile` or `Aggregate` block. Ampere and later report
+    /// SRAM/DRAM counters directly (Hopper splits SRAM uncorrectable into
+    /// Parity and SEC-DED); older GPUs report Single/Double Bit counters per
+    /// memory location, where "Device Memory" is the DRAM.
+    fn parse(block: &smi::SmiNode) -> Option<EccCounts> {
+        let mut counts = EccCounts::default();
+        let mut found = false;
+        for entry in &block.children {
+            let Some(value) = entry.value.as_deref().and_then(|v| v.parse::<u64>().ok()) else {
+                continue;
+            };
+            found = true;
+            match entry.key.as_str() {
+                "SRAM Correctable" => counts.sram_correctable += value,
+                k if k.starts_with("SRAM Uncorrectable") => counts.sram_uncorrectable += value,
+                "DRAM Correctable" => counts.dram_correctable += value,
+                "DRAM Uncorrectable" => counts.dram_uncorrectable += value,
+                _ => {}
+            }
+        }
+        for (section, correctable) in [("Single Bit", true), ("Double Bit", false)] {
+            let Some(bits) = block.child(section) else { continue };
+            for entry in &bits.children {
+                let Some(value) = entry.value.as_deref().and_then(|v| v.parse::<u64>().ok()) else {
+                    continue;
+                };
+                found = true;
+                match (entry.key.as_str(), correctable) {
+                    ("Total", _) => {}
+                    ("Device Memory", true) => counts.dram_correctable += value,
+                    ("Device Memory", false) => counts.dram_uncorrectable += value,
+                    (_, true) => counts.sram_correctable += value,
+                    (_, false) => counts.sram_uncorrectable += value,
+                }
+            }
+        }
+        found.then_some(counts)
+    }
+
+    fn needs_attention(&self) -> bool {
+        self.sram_correctable + self.sram_uncorrectable + self.dram_uncorrectable > 0
+    }
 }
 
-fn check_ecc_errors(log: &str) -> usize {
+fn check_ecc_errors(log: &str) -> CheckResult {
     println!("\n==================================================");
     println!("     ECC Memory Error Check");
     println!("==================================================\n");
-    
-    let dram_corr_re = Regex::new(r"DRAM Correctable\s*:\s*(\d+)").unwrap();
-    let dram_uncorr_re = Regex::new(r"DRAM Uncorrectable\s*:\s*(\d+)").unwrap();
-    
-    let mut total_correctable = 0;
-    let mut total_uncorrectable = 0;
-    
-    for cap in dram_corr_re.captures_iter(log) {
-        if let Ok(count) = cap[1].parse::<i32>() {
-            total_correctable += count;
+
+    let gpus = smi::parse_gpus(log);
+    let mut rows = Vec::new();
+    let mut disabled = Vec::new();
+    for gpu in &gpus {
+        if gpu.root.get(&["ECC Mode", "Current"]) == Some("Disabled") {
+            disabled.push(gpu.bus_id.clone());
+            continue;
+        }
+        let Some(errors) = gpu.root.child("ECC Errors") else { continue };
+        let volatile = errors.child("Volatile").and_then(EccCounts::parse);
+        let aggregate = errors.child("Aggregate").and_then(EccCounts::parse);
+        if volatile.is_some() || aggregate.is_some() {
+            rows.push((gpu, volatile, aggregate));
         }
     }
-    
-    for cap in dram_uncorr_re.captures_iter(log) {
-        if let Ok(count) = cap[1].parse::<i32>() {
-            total_uncorrectable += count;
+
+    if rows.is_empty() {
+        println!("No ECC error counters found in nvidia-smi output");
+        if !disabled.is_empty() {
+            println!("   ECC is disabled on: {}", disabled.join(", "));
+            return CheckResult::NotEvaluated(format!("ECC is disabled on {} GPU(s)", disabled.len()));
         }
+        return CheckResult::NotEvaluated("no ECC error counters in nvidia-smi output".to_string());
     }
-    
-    let total_errors = (total_correctable + total_uncorrectable) as usize;
-    
-    if total_errors > 0 {
-        println!("** ECC Errors Detected:");
-        println!("   DRAM Correctable Errors: {}", total_correctable);
-        println!("   DRAM Uncorrectable Errors: {}", total_uncorrectable);
-        if total_uncorrectable > 0 {
+
+    let fmt = |c: Option<EccCounts>, sram: bool| match c {
+        Some(c) if sram => format!("{}/{}", c.sram_correctable, c.sram_uncorrectable),
+        Some(c) => format!("{}/{}", c.dram_correctable, c.dram_uncorrectable),
+        None => "N/A".to_string(),
+    };
+
+    println!("Per-GPU ECC errors (correctable/uncorrectable):");
+    println!("   {:<4} {:<18} {:>14} {:>14} {:>14} {:>14}", "GPU", "Bus ID", "Vol SRAM", "Vol DRAM", "Agg SRAM", "Agg DRAM");
+    let mut flagged = 0;
+    let mut any_uncorrectable = false;
+    for (gpu, volatile, aggregate) in &rows {
+        let attention = volatile.is_some_and(|c| c.needs_attention()) || aggregate.is_some_and(|c| c.needs_attention());
+        any_uncorrectable |= [volatile, aggregate].iter()
+            .any(|c| c.is_some_and(|c| c.sram_uncorrectable + c.dram_uncorrectable > 0));
+        if attention {
+            flagged += 1;
+        }
+        println!("{} {:<4} {:<18} {:>14} {:>14} {:>14} {:>14}",
+            if attention { "**" } else { "  " },
+            gpu.index, gpu.bus_id,
+            fmt(*volatile, true), fmt(*volatile, false), fmt(*aggregate, true), fmt(*aggregate, false));
+    }
+
+    if !disabled.is_empty() {
+        println!("\n   ECC is disabled on: {}", disabled.join(", "));
+    }
+    if flagged > 0 {
+        println!("\n** {} GPU(s) with uncorrectable or SRAM ECC errors (marked **)", flagged);
+        if any_uncorrectable {
             println!("   ** CRITICAL: Uncorrectable errors indicate potential hardware failure!");
         }
     } else {
-        println!("No ECC memory errors detected");
+        println!("\nNo uncorrectable or SRAM ECC errors detected");
     }
-    
-    if log.contains("Retired Pages") {
-        println!("\nRetired Pages information available in log");
+
+    flagged.into()
+}
+
+/// NVIDIA retires at most 64 pages per GPU; the RMA policy treats 60 or more as exhausted.
+const RETIRED_PAGES_RMA_THRESHOLD: u64 = 60;
+
+fn check_row_remapping(log: &str) -> CheckResult {
+    println!("\n==================================================");
+    println!("     Row Remapping / Retired Pages Check");
+    println!("==================================================\n");
+
+    let gpus = smi::parse_gpus(log);
+    let mut findings = Vec::new();
+    let mut evaluated = 0;
+
+    for gpu in &gpus {
+        let name = format!("GPU {} ({})", gpu.index, gpu.bus_id);
+
+        // Ampere and later: row remapping replaces page retirement
+        if let Some(rows) = gpu.root.child("Remapped Rows").filter(|r| r.get(&["Pending"]).is_some()) {
+            evaluated += 1;
+            let correctable = rows.count(&["Correctable Error"]).unwrap_or(0);
+            let uncorrectable = rows.count(&["Uncorrectable Error"]).unwrap_or(0);
+            let pending = rows.get(&["Pending"]) == Some("Yes");
+            let failure = rows.get(&["Remapping Failure Occurred"]) == Some("Yes");
+            let histogram = |bucket: &str| rows.count(&["Bank Remap Availability Histogram", bucket]).unwrap_or(0);
+            println!("   {}: remapped rows {} correctable / {} uncorrectable, pending: {}, failure: {}, banks low/none: {}/{}",
+                name, correctable, uncorrectable,
+                if pending { "Yes" } else { "No" }, if failure { "Yes" } else { "No" },
+                histogram("Low"), histogram("None"));
+
+            if failure {
+                findings.push(format!("{} - RMA candidate: row remapping failure occurred", name));
+            } else if histogram("None") > 0 {
+                findings.push(format!("{} - RMA candidate: {} bank(s) have no spare rows left for remapping", name, histogram("None")));
+            }
+            if pending {
+                findings.push(format!("{} - reset required: row remap pending, reset the GPU to activate it", name));
+            }
+            continue;
+        }
+
+        // Pre-Ampere: dynamic page retirement
+        if let Some(pages) = gpu.root.child("Retired Pages") {
+            let single = pages.count(&["Single Bit ECC"]);
+            let double = pages.count(&["Double Bit ECC"]);
+            if single.is_none() && double.is_none() {
+                continue;
+            }
+            evaluated += 1;
+            let (single, double) = (single.unwrap_or(0), double.unwrap_or(0));
+            let pending = ["Pending Page Blacklist", "Pending Page Retirement", "Pending"].iter()
+                .any(|key| pages.get(&[key]) == Some("Yes"));
+            println!("   {}: retired pages {} single bit / {} double bit, pending: {}",
+                name, single, double, if pending { "Yes" } else { "No" });
+
+            if single + double >= RETIRED_PAGES_RMA_THRESHOLD {
+                findings.push(format!("{} - RMA candidate: {} retired pages (threshold {})", name, single + double, RETIRED_PAGES_RMA_THRESHOLD));
+            }
+            if pending {
+                findings.push(format!("{} - reset required: page retirement pending, reset the GPU or reload the driver", name));
+            }
+        }
     }
-    
-    total_errors
+
+    if evaluated == 0 {
+        println!("No row remapping or retired pages data found");
+        return CheckResult::NotEvaluated("no row remapping or retired pages data in nvidia-smi output".to_string());
+    }
+
+    if findings.is_empty() {
+        println!("\nNo pending remaps, remapping failures or retired page exhaustion detected");
+    } else {
+        println!("\n** Row Remapping / Retired Pages Issues: {}", findings.len());
+        for finding in &findings {
+            println!("   {}", finding);
+        }
+        println!("   See https://docs.nvidia.com/deploy/a100-gpu-mem-error-mgmt/index.html");
+    }
+
+    findings.len().into()
 }
 
-fn check_nic_pcie_status(log: &str) -> usize {
+fn check_nic_pcie_status(log: &str) -> CheckResult {
     println!("\n==================================================");
     println!("     NIC PCIe Status Check");
     println!("==================================================\n");
@@ -470,6 +1088,11 @@ fn check_nic_pcie_status(log: &str) -> usize {
         let bus_short = &bus_id[..5];
         found_nics.insert(bus_short.to_string(), (format!("{} GT/s", speed), width));
     }
+
+    if found_nics.is_empty() {
+        println!("No mlx5_core PCIe bandwidth messages found");
+        return CheckResult::NotEvaluated("no mlx5_core PCIe bandwidth messages in the kernel log".to_string());
+    }
     
     let mut issues = Vec::new();
     
@@ -501,56 +1124,98 @@ fn check_nic_pcie_status(log: &str) -> usize {
         }
     }
     
-    issues.len()
+    issues.len().into()
 }
 
-fn check_gpu_power_performance(log: &str) -> usize {
+fn check_gpu_power_performance(log: &str) -> CheckResult {
     println!("\n==================================================");
     println!("     GPU Power and Performance");
     println!("==================================================\n");
-    
-    let power_draw_re = Regex::new(r"GPU Power Readings\s*\n\s*Power Draw\s*:\s*([\d.]+)\s*W").unwrap();
-    let power_limit_re = Regex::new(r"Current Power Limit\s*:\s*([\d.]+)\s*W").unwrap();
-    let persistence_re = Regex::new(r"Persistence Mode\s*:\s*(\w+)").unwrap();
-    
-    let mut power_draws = Vec::new();
-    let mut power_limits = Vec::new();
-    
-    for cap in power_draw_re.captures_iter(log) {
-        if let Ok(power) = cap[1].parse::<f32>() {
-            power_draws.push(power);
+
+    let gpus = smi::parse_gpus(log);
+    let readings: Vec<_> = gpus.iter()
+        .filter_map(|gpu| gpu.root.child("GPU Power Readings").or_else(|| gpu.root.child("Power Readings")).map(|p| (gpu, p)))
+        .collect();
+
+    if readings.is_empty() {
+        println!("No GPU power readings found");
+        return CheckResult::NotEvaluated("no GPU power readings in nvidia-smi output".to_string());
+    }
+
+    let watts = |v: Option<f64>| v.map_or("N/A".to_string(), |v| format!("{:.0} W", v));
+    println!("   {:<4} {:<18} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "GPU", "Bus ID", "PState", "Draw", "Current", "Enforced", "Default", "Min", "Max");
+
+    let mut limits = Vec::new();
+    for (gpu, p) in &readings {
+        let draw = ["Power Draw", "Instantaneous Power Draw", "Average Power Draw"].iter().find_map(|k| p.number(&[k]));
+        let current = p.number(&["Current Power Limit"]).or_else(|| p.number(&["Power Limit"]));
+        let enforced = p.number(&["Enforced Power Limit"]).or(current);
+        let default = p.number(&["Default Power Limit"]);
+        println!("   {:<4} {:<18} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            gpu.index, gpu.bus_id, gpu.root.get(&["Performance State"]).unwrap_or("N/A"),
+            watts(draw), watts(current), watts(enforced), watts(default),
+            watts(p.number(&["Min Power Limit"])), watts(p.number(&["Max Power Limit"])));
+        limits.push((gpu, enforced, default));
+    }
+
+    let mut issues = Vec::new();
+    let mut below_default = Vec::new();
+    for (gpu, enforced, default) in &limits {
+        if let (Some(enforced), Some(default)) = (enforced, default) {
+            if enforced < default {
+                below_default.push(gpu.index);
+                issues.push(format!("GPU {} ({}) enforced power limit {:.0} W is below the default {:.0} W",
+                    gpu.index, gpu.bus_id, enforced, default));
+            }
         }
     }
-    
-    for cap in power_limit_re.captures_iter(log) {
-        if let Ok(limit) = cap[1].parse::<f32>() {
-            power_limits.push(limit);
+
+    // GPUs of the same model in one chassis should run with the same limit
+    let mut by_model: HashMap<&str, Vec<(usize, &str, i64)>> = HashMap::new();
+    for (gpu, enforced, _) in &limits {
+        if let Some(enforced) = enforced {
+            let model = gpu.root.get(&["Product Name"]).unwrap_or("Unknown");
+            by_model.entry(model).or_default().push((gpu.index, gpu.bus_id.as_str(), enforced.round() as i64));
         }
     }
-    
-    if !power_draws.is_empty() {
-        let avg_power = power_draws.iter().sum::<f32>() / power_draws.len() as f32;
-        println!("Average GPU Power Draw: {:.2} W", avg_power);
-        
-        if !power_limits.is_empty() {
-            let avg_limit = power_limits.iter().sum::<f32>() / power_limits.len() as f32;
-            let usage_pct = (avg_power / avg_limit) * 100.0;
-            println!("Average Power Limit: {:.2} W ({:.1}% utilized)", avg_limit, usage_pct);
+    for (model, entries) in &by_model {
+        let counts = count_occurrences(entries.iter().map(|(_, _, limit)| limit.to_string()));
+        let Some((common, _)) = counts.iter().max_by_key(|(limit, count)| (**count, limit.parse::<i64>().unwrap_or(0))) else {
+            continue;
+        };
+        // A GPU already reported below its default is one issue, not two
+        for (index, bus_id, limit) in entries.iter().filter(|(index, _, _)| !below_default.contains(index)) {
+            if limit.to_string() != *common {
+                issues.push(format!("GPU {} ({}) enforced power limit {} W differs from the {} W used by the other {} GPUs",
+                    index, bus_id, limit, common, model));
+            }
         }
     }
-    
-    let persistence_disabled = persistence_re.captures_iter(log)
-        .filter(|cap| &cap[1] == "Disabled")
-        .count();
-    
-    if persistence_disabled > 0 {
-        println!("\n** RECOMMENDATION: Persistence Mode is Disabled on {} GPU(s)", persistence_disabled);
+
+    if issues.is_empty() {
+        println!("\nPower limits are at their defaults and consistent across GPUs");
+    } else {
+        println!("\n** GPU Power Limit Issues: {}", issues.len());
+        for issue in &issues {
+            println!("   {}", issue);
+        }
+        println!("   Restore with: sudo nvidia-smi -i <index> -pl <default watts>");
+    }
+
+    let persistence_disabled: Vec<_> = gpus.iter()
+        .filter(|gpu| gpu.root.get(&["Persistence Mode"]) == Some("Disabled"))
+        .map(|gpu| gpu.index.to_string())
+        .collect();
+
+    if !persistence_disabled.is_empty() {
+        println!("\n** RECOMMENDATION: Persistence Mode is Disabled on {} GPU(s): {}", persistence_disabled.len(), persistence_disabled.join(", "));
         println!("   Enable with: sudo nvidia-smi -pm 1");
         println!("   This improves performance and reduces driver load time.");
     }
-    
+
     // Persistence Mode disabled is a recommendation, not an issue
-    0
+    issues.len().into()
 }
 
 fn extract_field<'a>(block: &'a str, field: &str) -> &'a str {
@@ -561,11 +1226,77 @@ fn extract_field<'a>(block: &'a str, field: &str) -> &'a str {
         .unwrap_or("")
 }
 
-fn print_summary_item(name: &str, count: usize) {
-    if count > 0 {
-        println!("  ❌ {}: {} issue(s) found", name, count);
-    } else {
-        println!("  ✅ {}: OK", name);
+/// Prints left-aligned columns sized to their widest cell.
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: Vec<&str>| {
+        cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("   {}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("   {}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
+
+/// Knowledge base entries for every triggered check and every Xid code seen.
+fn print_remediation(log: &str, remedies: &remediation::KnowledgeBase, results: &[(&str, CheckResult)]) {
+    let mut xids: Vec<u32> = RE_XID.captures_iter(log).filter_map(|cap| cap[1].parse().ok()).collect();
+    xids.sort();
+    xids.dedup();
+
+    let checks: Vec<(&str, &remediation::Remedy)> = results.iter()
+        .filter(|(name, result)| result.issues() > 0 && *name != "Xid Errors")
+        .filter_map(|(name, _)| remedies.check(name).map(|r| (*name, r)))
+        .collect();
+    let xids: Vec<(String, &remediation::Remedy)> = xids.iter()
+        .filter_map(|xid| remedies.xid(&xid.to_string()).map(|r| (format!("Xid {}", xid), r)))
+        .collect();
+    if checks.is_empty() && xids.is_empty() {
+        return;
+    }
+
+    println!("\n  Remediation:");
+    for (name, remedy) in checks {
+        remediation::print_remedy(name, remedy);
+    }
+    for (name, remedy) in &xids {
+        remediation::print_remedy(name, remedy);
+    }
+}
+
+fn redact_file(input: &Path, output: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let mut redactor = redact::Redactor::default();
+    let redacted = redactor.redact(&input::read(input)?.content.replace('\r', ""));
+    let counts: Vec<String> = redactor.counts().iter().map(|(kind, count)| format!("{} {}", count, kind)).collect();
+    let counts = if counts.is_empty() { "nothing to redact".to_string() } else { counts.join(", ") };
+
+    if output.is_none() && input.as_os_str() == "-" {
+        print!("{}", redacted);
+        eprintln!("Redacted {}", counts);
+        return Ok(());
+    }
+    let output = output.map_or_else(|| PathBuf::from(format!("{}.redacted", input.display())), Path::to_path_buf);
+    fs::write(&output, redacted)?;
+    println!("Wrote {} ({})", output.display(), counts);
+    Ok(())
+}
+
+fn print_summary_item(name: &str, result: &CheckResult) {
+    match result {
+        CheckResult::Ok => println!("  ✅ {}: OK", name),
+        CheckResult::Issues(count) => println!("  ❌ {}: {} issue(s) found", name, count),
+        CheckResult::NotEvaluated(reason) => println!("  ⚪ {}: not evaluated ({})", name, reason),
+        CheckResult::NotApplicable(reason) => println!("  ➖ {}: not applicable ({})", name, reason),
     }
 }
 
@@ -584,13 +1315,43 @@ where
 fn load_xid_descriptions(csv_path: &Path) -> HashMap<String, String> {
     let mut xid_map = HashMap::new();
     if let Ok(mut reader) = csv::ReaderBuilder::new().has_headers(false).from_path(csv_path) {
-        for result in reader.records() {
-            if let Ok(record) = result {
-                if record.len() >= 2 {
-                    xid_map.insert(record.get(0).unwrap().to_string(), record.get(1).unwrap().to_string());
-                }
+        for record in reader.records().flatten() {
+            if record.len() >= 2 {
+                xid_map.insert(record.get(0).unwrap().to_string(), record.get(1).unwrap().to_string());
             }
         }
     }
     xid_map
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEP: &str = "____________________________________________\n";
+
+    #[test]
+    fn xid_in_dmesg_and_journal_counts_once() {
+        let log = format!("{SEP}\n*** journalctl -b -0 -k\nOct 14 node kernel: NVRM: Xid (PCI:0000:3b:00): 79, pid=1, GPU has fallen off the bus.\n\
+            {SEP}\n*** dmesg\n[  600.000000] NVRM: Xid (PCI:0000:3b:00): 79, pid=1, GPU has fallen off the bus.\n");
+        assert_eq!(xid_events(&log, &boot::BootMap::parse(&log)).len(), 1);
+    }
+
+    #[test]
+    fn repeated_xid_within_a_section_is_kept() {
+        let log = format!("{SEP}\n*** dmesg\n[  600.000000] NVRM: Xid (PCI: 0000:3b:00): 13, Graphics Exception\n\
+            [  601.000000] NVRM: Xid (PCI: 0000:3b:00): 13, Graphics Exception\n");
+        assert_eq!(xid_events(&log, &boot::BootMap::parse(&log)).len(), 2);
+    }
+
+    #[test]
+    fn plain_dmesg_lines_match_and_count_once() {
+        let log = format!("{SEP}\n*** dmesg\n[  600.000000] NVRM: GPU 0000:3b:00.0: GPU has fallen off the bus.\n\
+            [  601.000000] nvidia-gpu 0000:3b:00.3: Refused to change power state, currently in D3\n\
+            {SEP}\n*** journalctl -b -0 -k\nOct 14 10:00:00 node kernel: NVRM: GPU 0000:3b:00.0: GPU has fallen off the bus.\n\
+            Oct 14 10:00:01 node kernel: nvidia-gpu 0000:3b:00.3: Refused to change power state, currently in D3\n");
+        let boots = boot::BootMap::parse(&log);
+        assert_eq!(check_fallen_off_bus(&log, &boots), 1);
+        assert_eq!(check_power_state_refused(&log, &boots), 1);
+    }
+}
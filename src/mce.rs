@@ -0,0 +1,213 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+use crate::boot::{self, BootMap};
+
+static RE_MCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"CPU (\d+): Machine Check(?: Exception)?: [0-9a-fx]+ Bank (\d+): ([0-9a-fA-F]{16})").unwrap());
+static RE_MCE_ADDR: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[Hardware Error\]:.*\bADDR ([0-9a-fA-Fx]+)").unwrap());
+static RE_EDAC: Lazy<Regex> = Lazy::new(|| Regex::new(r"EDAC (?:\w+ )?MC(\d+): (\d+) (CE|UE) ([^\n]*?) on ([^\s(]+)([^\n]*)").unwrap());
+static RE_EDAC_CHANNEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"channel:(\d+)").unwrap());
+static RE_EDAC_LABEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"label:\s*(\S+)").unwrap());
+static RE_HARDWARE_ERROR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^.*Hardware Error.*$").unwrap());
+
+const MCI_STATUS_UC: u64 = 1 << 61;
+const MCI_STATUS_PCC: u64 = 1 << 57;
+
+/// One machine check as logged by the kernel mce driver.
+#[derive(Debug, Clone)]
+pub struct MceEvent {
+    pub cpu: u32,
+    pub bank: u32,
+    pub status: u64,
+    pub address: Option<String>,
+    /// Byte offset of the event in the log.
+    pub pos: usize,
+}
+
+impl MceEvent {
+    pub fn uncorrected(&self) -> bool {
+        self.status & MCI_STATUS_UC != 0
+    }
+
+    /// Processor context corrupt: the machine could not continue.
+    pub fn fatal(&self) -> bool {
+        self.status & MCI_STATUS_PCC != 0
+    }
+}
+
+/// A memory error reported by an EDAC memory controller driver.
+#[derive(Debug, Clone)]
+pub struct EdacEvent {
+    pub controller: u32,
+    pub count: u64,
+    pub uncorrected: bool,
+    pub location: String,
+    pub channel: Option<u32>,
+    pub label: String,
+    pub pos: usize,
+}
+
+pub fn parse_mce_events(log: &str) -> Vec<MceEvent> {
+    let mut events: Vec<MceEvent> = Vec::new();
+    let mut pos = 0;
+    for line in log.split_inclusive('\n') {
+        let start = pos;
+        pos += line.len();
+        if let Some(cap) = RE_MCE.captures(line) {
+            events.push(MceEvent {
+                cpu: cap[1].parse().unwrap_or(0),
+                bank: cap[2].parse().unwrap_or(0),
+                status: u64::from_str_radix(&cap[3], 16).unwrap_or(0),
+                address: None,
+                pos: start,
+            });
+        } else if let Some(cap) = RE_MCE_ADDR.captures(line) {
+            // The address follows on the "TSC ... ADDR ..." line of the same event
+            if let Some(event) = events.last_mut().filter(|e| e.address.is_none()) {
+                event.address = Some(cap[1].to_string());
+            }
+        }
+    }
+    events
+}
+
+pub fn parse_edac_events(log: &str) -> Vec<EdacEvent> {
+    RE_EDAC.captures_iter(log)
+        .map(|cap| {
+            let details = &cap[6];
+            let location = cap[5].to_string();
+            EdacEvent {
+                controller: cap[1].parse().unwrap_or(0),
+                count: cap[2].parse().unwrap_or(1),
+                uncorrected: &cap[3] == "UE",
+                channel: RE_EDAC_CHANNEL.captures(details).and_then(|c| c[1].parse().ok()),
+                label: RE_EDAC_LABEL.captures(details).map_or_else(|| location.clone(), |c| c[1].to_string()),
+                location,
+                pos: cap.get(0).unwrap().start(),
+            }
+        })
+        .collect()
+}
+
+/// Finds the dmidecode Memory Device whose locator matches an EDAC label,
+/// returning a short description of the installed DIMM.
+fn find_dimm(log: &str, label: &str) -> Option<String> {
+    let normalize = |s: &str| s.trim().to_uppercase().trim_start_matches("DIMM").trim_start_matches(['_', '-', ' ']).to_string();
+    let wanted = normalize(label);
+    crate::RE_DIMM.captures_iter(log)
+        .find(|block| {
+            let locator = crate::extract_field(&block[1], "\tLocator:");
+            !locator.is_empty() && normalize(locator) == wanted
+        })
+        .map(|block| {
+            let block = &block[1];
+            format!("{} {} {} SN {}",
+                crate::extract_field(block, "Size:"),
+                crate::extract_field(block, "Manufacturer:"),
+                crate::extract_field(block, "Part Number:"),
+                crate::extract_field(block, "Serial Number:"))
+        })
+}
+
+pub fn check_hardware_errors(log: &str, boots: &BootMap) -> usize {
+    println!("\n==================================================");
+    println!("     Machine Check / EDAC Memory Errors");
+    println!("==================================================\n");
+
+    // dmesg and the journal log the same events
+    let mce_events = boots.unique(log, parse_mce_events(log).into_iter().map(|e| (e.pos, e)));
+    let edac_events = boots.unique(log, parse_edac_events(log).into_iter().map(|e| (e.pos, e)));
+    let mut issues = 0;
+
+    if !mce_events.is_empty() {
+        // bank -> (corrected, uncorrected, fatal, cpus)
+        let mut banks: BTreeMap<u32, (usize, usize, usize, Vec<u32>)> = BTreeMap::new();
+        for event in &mce_events {
+            let entry = banks.entry(event.bank).or_default();
+            if event.uncorrected() {
+                entry.1 += 1;
+            } else {
+                entry.0 += 1;
+            }
+            if event.fatal() {
+                entry.2 += 1;
+            }
+            if !entry.3.contains(&event.cpu) {
+                entry.3.push(event.cpu);
+            }
+        }
+        println!("** Machine Check Events: {}", mce_events.len());
+        println!("   {:<6} {:>10} {:>12} {:>6}  CPUs", "Bank", "Corrected", "Uncorrected", "Fatal");
+        for (bank, (corrected, uncorrected, fatal, cpus)) in &banks {
+            let cpus: Vec<String> = cpus.iter().map(u32::to_string).collect();
+            println!("   {:<6} {:>10} {:>12} {:>6}  {}", bank, corrected, uncorrected, fatal, cpus.join(","));
+            issues += uncorrected + usize::from(*corrected > 0);
+        }
+        for event in mce_events.iter().filter(|e| e.uncorrected()) {
+            println!("   Uncorrected: CPU {} Bank {} status 0x{:016x}{}", event.cpu, event.bank, event.status,
+                event.address.as_ref().map_or(String::new(), |a| format!(" addr 0x{}", a.trim_start_matches("0x"))));
+        }
+        boot::print_breakdown(boots, mce_events.iter().map(|e| e.pos));
+        println!("   Decode the status with `mcelog --ascii` or `rasdaemon` for the failing unit.");
+    }
+
+    if !edac_events.is_empty() {
+        // label -> (controller, channel, location, corrected, uncorrected)
+        let mut dimms: BTreeMap<&str, (u32, Option<u32>, &str, u64, u64)> = BTreeMap::new();
+        for event in &edac_events {
+            let entry = dimms.entry(&event.label).or_insert((event.controller, event.channel, &event.location, 0, 0));
+            if event.uncorrected {
+                entry.4 += event.count;
+            } else {
+                entry.3 += event.count;
+            }
+        }
+        println!("\n** EDAC Memory Errors: {} event(s) on {} DIMM(s)", edac_events.len(), dimms.len());
+        for (label, (controller, channel, location, corrected, uncorrected)) in &dimms {
+            let installed = find_dimm(log, label).map_or(String::new(), |d| format!(" -> {}", d));
+            let location = if location == label { String::new() } else { format!(" ({})", location) };
+            println!("   {}MC{} channel {} DIMM {}{}: {} CE, {} UE{}",
+                if *uncorrected > 0 { "** " } else { "" },
+                controller, channel.map_or("?".to_string(), |c| c.to_string()), label, location, corrected, uncorrected, installed);
+            issues += 1;
+        }
+        boot::print_breakdown(boots, edac_events.iter().map(|e| e.pos));
+        println!("   Replace DIMMs with uncorrectable errors; repeated correctable errors on one DIMM predict failure.");
+    }
+
+    if mce_events.is_empty() && edac_events.is_empty() {
+        // Fall back to listing unparsed hardware error lines (e.g. APEI/GHES reports)
+        let matches = boots.unique(log, RE_HARDWARE_ERROR.find_iter(log).map(|m| (m.start(), m)));
+        let lines: Vec<&str> = matches.iter().map(|m| m.as_str().trim()).collect();
+        if lines.is_empty() {
+            println!("No Hardware Errors found");
+            return 0;
+        }
+        println!("** Hardware Errors: {}", lines.len());
+        for (line, count) in crate::count_occurrences(lines.iter()) {
+            println!("    ({}x) {}", count, line);
+        }
+        boot::print_breakdown(boots, matches.iter().map(|m| m.start()));
+        return lines.len();
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEP: &str = "____________________________________________\n";
+
+    #[test]
+    fn dmesg_and_journal_copies_count_once() {
+        let log = format!("{SEP}\n*** dmesg\n[  100.000000] mce: [Hardware Error]: CPU 3: Machine Check: 0 Bank 5: bea0000000000108\n\
+            [  101.000000] EDAC MC0: 1 CE memory read error on CPU_SrcID#0_MC#0_Chan#1_DIMM#0 (channel:1 slot:0 page:0x1 offset:0x0 grain:32 syndrome:0x0)\n\
+            {SEP}\n*** journalctl -b -0 -k\nOct 14 10:00:00 node kernel: mce: [Hardware Error]: CPU 3: Machine Check: 0 Bank 5: bea0000000000108\n\
+            Oct 14 10:00:01 node kernel: EDAC MC0: 1 CE memory read error on CPU_SrcID#0_MC#0_Chan#1_DIMM#0 (channel:1 slot:0 page:0x1 offset:0x0 grain:32 syndrome:0x0)\n");
+        // One uncorrected bank 5 event plus one DIMM
+        assert_eq!(check_hardware_errors(&log, &BootMap::parse(&log)), 2);
+    }
+}
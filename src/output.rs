@@ -0,0 +1,19 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::redact::Redactor;
+
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// Redacts every report line printed from now on with the values `redactor` has seen.
+pub fn redact_with(redactor: Redactor) {
+    let _ = REDACTOR.set(redactor);
+}
+
+/// Prints one line of the report; `println!` in this crate expands to this.
+pub fn print(args: fmt::Arguments) {
+    match REDACTOR.get() {
+        Some(redactor) => std::println!("{}", redactor.redact_seen(&args.to_string())),
+        None => std::println!("{}", args),
+    }
+}
@@ -0,0 +1,326 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::boot::{self, BootMap};
+use crate::smi::short_bus_id;
+
+static RE_LSPCI_DEVICE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7]) ([^:\n]+): ([^\n]+)$").unwrap());
+static RE_LSPCI_BRIDGE_BUS: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7]) [^\n]*bridge[^\n]*\n(?:[ \t][^\n]*\n)*?[ \t]+Bus: primary=[0-9a-fA-F]+, secondary=([0-9a-fA-F]+), subordinate=([0-9a-fA-F]+)").unwrap());
+pub static RE_AER_RECEIVED: Lazy<Regex> = Lazy::new(|| Regex::new(r"AER: (?:Multiple )?(Corrected|Uncorrected \((?:Non-Fatal|Fatal)\)|Uncorrected) error (?:received|message received from): (?:id=)?(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7])").unwrap());
+static RE_AER_BUS_ERROR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7]):(?: AER:)? PCIe Bus Error: severity=([^,]+), type=([^,]+)").unwrap());
+pub static RE_AER_BIT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7]):\s+\[\s*\d+\]\s+([A-Za-z][\w ]*?)\s*(?:\(First\))?\s*$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Gpu,
+    Nic,
+    NvSwitch,
+    Bridge,
+    Other,
+}
+
+impl DeviceKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeviceKind::Gpu => "GPU",
+            DeviceKind::Nic => "NIC",
+            DeviceKind::NvSwitch => "NVSwitch",
+            DeviceKind::Bridge => "PCIe switch/port",
+            DeviceKind::Other => "device",
+        }
+    }
+}
+
+/// A PCI device from lspci, keyed by its short bus ID.
+#[derive(Debug, Clone)]
+pub struct PciDevice {
+    pub name: String,
+    pub kind: DeviceKind,
+}
+
+/// PCI devices from every lspci listing in the report, plus the bus ranges
+/// behind each bridge from `lspci -vvv` so devices can be tied to their upstream ports.
+#[derive(Debug, Default)]
+pub struct PciTopology {
+    pub devices: HashMap<String, PciDevice>,
+    bridges: Vec<(String, u32, u32)>,
+}
+
+impl PciTopology {
+    pub fn parse(log: &str) -> PciTopology {
+        let mut devices = HashMap::new();
+        for cap in RE_LSPCI_DEVICE.captures_iter(log) {
+            let class = cap[2].trim().to_string();
+            let name = cap[3].trim().to_string();
+            let nvidia = name.contains("NVIDIA");
+            let kind = if nvidia && (class.contains("3D") || class.contains("VGA") || class.contains("Display")) {
+                DeviceKind::Gpu
+            } else if nvidia && class.starts_with("Bridge") {
+                DeviceKind::NvSwitch
+            } else if class.contains("Ethernet") || class.contains("Infiniband") || class.contains("Network") {
+                DeviceKind::Nic
+            } else if class.contains("bridge") {
+                DeviceKind::Bridge
+            } else {
+                DeviceKind::Other
+            };
+            devices.entry(cap[1].to_lowercase()).or_insert(PciDevice { name, kind });
+        }
+        let bridges = RE_LSPCI_BRIDGE_BUS.captures_iter(log)
+            .filter_map(|cap| Some((
+                cap[1].to_lowercase(),
+                u32::from_str_radix(&cap[2], 16).ok()?,
+                u32::from_str_radix(&cap[3], 16).ok()?,
+            )))
+            .collect();
+        PciTopology { devices, bridges }
+    }
+
+    pub fn kind(&self, bus_id: &str) -> DeviceKind {
+        self.devices.get(&short_bus_id(bus_id)).map_or(DeviceKind::Other, |d| d.kind)
+    }
+
+    /// "GPU NVIDIA Corporation GH100 [H100 SXM5 80GB]" style description, or "unknown device".
+    pub fn describe(&self, bus_id: &str) -> String {
+        self.devices.get(&short_bus_id(bus_id))
+            .map_or("unknown device".to_string(), |d| format!("{} {}", d.kind.label(), d.name))
+    }
+
+    /// Bridges whose secondary..subordinate bus range contains `bus_id`.
+    pub fn upstream_ports(&self, bus_id: &str) -> Vec<&str> {
+        let Some(bus) = short_bus_id(bus_id).split(':').next().and_then(|b| u32::from_str_radix(b, 16).ok()) else {
+            return Vec::new();
+        };
+        self.bridges.iter()
+            .filter(|(_, secondary, subordinate)| (*secondary..=*subordinate).contains(&bus))
+            .map(|(bridge, _, _)| bridge.as_str())
+            .collect()
+    }
+
+    /// The GPU behind this device, if it is a GPU or one of the ports leading to one.
+    pub fn gpu_for(&self, bus_id: &str) -> Option<String> {
+        let bus_id = short_bus_id(bus_id);
+        if self.kind(&bus_id) == DeviceKind::Gpu {
+            return Some(bus_id);
+        }
+        let mut gpus: Vec<&String> = self.devices.iter()
+            .filter(|(id, d)| d.kind == DeviceKind::Gpu && self.upstream_ports(id).contains(&bus_id.as_str()))
+            .map(|(id, _)| id)
+            .collect();
+        gpus.sort();
+        (gpus.len() == 1).then(|| gpus[0].clone())
+    }
+}
+
+#[derive(Debug, Default)]
+struct AerDevice {
+    corrected: usize,
+    nonfatal: usize,
+    fatal: usize,
+    layers: Vec<String>,
+    types: BTreeMap<String, usize>,
+}
+
+/// AER errors per reporting device, with the log position of every error record.
+fn parse_aer(log: &str, boots: &BootMap) -> (BTreeMap<String, AerDevice>, Vec<usize>) {
+    let mut devices: BTreeMap<String, AerDevice> = BTreeMap::new();
+    let mut positions = Vec::new();
+
+    // Detailed "PCIe Bus Error" records name the device that logged the error;
+    // without them, fall back to the source ID of the "error received" summary.
+    let detailed = RE_AER_BUS_ERROR.is_match(log);
+    let mut lines = Vec::new();
+    let mut pos = 0;
+    for line in log.split_inclusive('\n') {
+        if RE_AER_BUS_ERROR.is_match(line) || RE_AER_RECEIVED.is_match(line) || RE_AER_BIT.is_match(line) {
+            lines.push((pos, (pos, line.trim_end())));
+        }
+        pos += line.len();
+    }
+    // dmesg and the journal log the same errors
+    for (start, line) in boots.unique(log, lines) {
+        let record = if detailed {
+            RE_AER_BUS_ERROR.captures(line).map(|cap| (cap[1].to_lowercase(), cap[2].trim().to_string(), Some(cap[3].trim().to_string())))
+        } else {
+            RE_AER_RECEIVED.captures(line).map(|cap| (cap[2].to_lowercase(), cap[1].to_string(), None))
+        };
+        if let Some((bus_id, severity, layer)) = record {
+            positions.push(start);
+            let device = devices.entry(bus_id).or_default();
+            if severity.contains("Fatal") && !severity.contains("Non-Fatal") {
+                device.fatal += 1;
+            } else if severity.starts_with("Uncorrected") {
+                device.nonfatal += 1;
+            } else {
+                device.corrected += 1;
+            }
+            if let Some(layer) = layer.filter(|l| !device.layers.contains(l)) {
+                device.layers.push(layer);
+            }
+        } else if let Some(cap) = RE_AER_BIT.captures(line) {
+            if let Some(device) = devices.get_mut(&cap[1].to_lowercase()) {
+                *device.types.entry(cap[2].to_string()).or_default() += 1;
+            }
+        }
+    }
+    (devices, positions)
+}
+
+pub fn check_pcie_aer(log: &str, boots: &BootMap) -> usize {
+    println!("\n==================================================");
+    println!("     PCIe AER Error Check");
+    println!("==================================================\n");
+
+    let topology = PciTopology::parse(log);
+    let (devices, positions) = parse_aer(log, boots);
+
+    if devices.is_empty() {
+        println!("No PCIe AER errors found");
+        return 0;
+    }
+
+    let mut critical = Vec::new();
+    println!("** PCIe AER Errors on {} device(s):", devices.len());
+    for (bus_id, device) in &devices {
+        let types: Vec<String> = device.types.iter().map(|(name, count)| format!("{} x{}", name, count)).collect();
+        println!("   {} {}: {} corrected, {} non-fatal, {} fatal{}{}",
+            bus_id, topology.describe(bus_id), device.corrected, device.nonfatal, device.fatal,
+            if device.layers.is_empty() { String::new() } else { format!(" ({})", device.layers.join(", ")) },
+            if types.is_empty() { String::new() } else { format!(" [{}]", types.join(", ")) });
+
+        if device.nonfatal + device.fatal > 0 {
+            if let Some(gpu) = topology.gpu_for(bus_id) {
+                critical.push(if gpu == *bus_id {
+                    format!("Uncorrected AER errors on GPU {}", gpu)
+                } else {
+                    format!("Uncorrected AER errors on {} upstream of GPU {}", bus_id, gpu)
+                });
+            }
+        }
+    }
+    boot::print_breakdown(boots, positions.into_iter());
+
+    if !critical.is_empty() {
+        println!("\n** CRITICAL:");
+        for line in &critical {
+            println!("   {}", line);
+        }
+        println!("   Uncorrected errors on a GPU link commonly precede Xid 79 (GPU has fallen off the bus).");
+        println!("   Reseat the GPU/riser and check the PCIe switch and cabling before replacing the GPU.");
+    }
+
+    devices.len()
+}
+
+static RE_DMAR_FAULT: Lazy<Regex> = Lazy::new(|| Regex::new(r"DMAR: ?\[(DMA \w+|INTR-REMAP)[^\]]*\] Request device \[(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7])\]").unwrap());
+static RE_DMAR_REASON: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[fault reason (?:0x)?([0-9a-fA-F]+)\]\s*(.*)$").unwrap());
+static RE_AMD_VI_FAULT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7]): )?AMD-Vi: Event logged \[(IO_PAGE_FAULT|INVALID_DEVICE_REQUEST|DEV_TAB_HARDWARE_ERROR|PAGE_TAB_HARDWARE_ERROR)(?: device=(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7]))?").unwrap());
+static RE_FAULT_ADDRESS: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:fault addr |address=)(0x[0-9a-fA-F]+|[0-9a-fA-F]+)").unwrap());
+
+/// device -> fault type -> (count, first fault address)
+type IommuFaults = BTreeMap<String, BTreeMap<String, (usize, Option<String>)>>;
+
+/// IOMMU faults per device, with the log position of every fault.
+fn parse_iommu_faults(log: &str, boots: &BootMap) -> (IommuFaults, Vec<usize>) {
+    let mut records = Vec::new();
+    let mut pos = 0;
+    for line in log.split_inclusive('\n') {
+        let start = pos;
+        pos += line.len();
+        let line = line.trim_end();
+        let fault = if let Some(cap) = RE_DMAR_FAULT.captures(line) {
+            let kind = match RE_DMAR_REASON.captures(line) {
+                Some(reason) if !reason[2].trim().is_empty() => format!("{} (reason 0x{}: {})", &cap[1], &reason[1], reason[2].trim()),
+                Some(reason) => format!("{} (reason 0x{})", &cap[1], &reason[1]),
+                None => cap[1].to_string(),
+            };
+            Some((cap[2].to_lowercase(), kind))
+        } else if let Some(cap) = RE_AMD_VI_FAULT.captures(line) {
+            cap.get(3).or(cap.get(1)).map(|device| (device.as_str().to_lowercase(), cap[2].to_string()))
+        } else {
+            None
+        };
+        if let Some((device, kind)) = fault {
+            let address = RE_FAULT_ADDRESS.captures(line).map(|cap| cap[1].to_string());
+            records.push((start, (start, device, kind, address)));
+        }
+    }
+
+    let mut faults = IommuFaults::new();
+    let mut positions = Vec::new();
+    // dmesg and the journal log the same faults
+    for (start, device, kind, address) in boots.unique(log, records) {
+        positions.push(start);
+        let entry = faults.entry(device).or_default().entry(kind).or_insert((0, address));
+        entry.0 += 1;
+    }
+    (faults, positions)
+}
+
+pub fn check_iommu_faults(log: &str, boots: &BootMap) -> usize {
+    println!("\n==================================================");
+    println!("     IOMMU / DMAR Fault Check");
+    println!("==================================================\n");
+
+    let topology = PciTopology::parse(log);
+    let (faults, positions) = parse_iommu_faults(log, boots);
+
+    if faults.is_empty() {
+        println!("No IOMMU/DMAR faults found");
+        return 0;
+    }
+
+    println!("** IOMMU Faults on {} device(s):", faults.len());
+    let mut gpu_or_nic = false;
+    for (device, kinds) in &faults {
+        let kind = topology.kind(device);
+        gpu_or_nic |= matches!(kind, DeviceKind::Gpu | DeviceKind::Nic);
+        let total: usize = kinds.values().map(|(count, _)| count).sum();
+        println!("   {} {}: {} fault(s)", device, topology.describe(device), total);
+        for (fault, (count, address)) in kinds {
+            println!("      ({}x) {}{}", count, fault, address.as_ref().map_or(String::new(), |a| format!(", first addr {}", a)));
+        }
+    }
+    boot::print_breakdown(boots, positions.into_iter());
+
+    if gpu_or_nic {
+        println!("\n   IOMMU faults on GPUs/NICs break GPUDirect RDMA and peer-to-peer transfers.");
+        let passthrough = crate::cmdline::find(log).is_some_and(|c| c.value("iommu") == Some("pt"));
+        if !passthrough {
+            println!("   Add iommu=pt to the kernel command line (see the Kernel Command Line check),");
+        }
+        println!("   and disable ACS on the PCIe switches between GPUs and NICs (BIOS setting or `setpci ... ECAP_ACS+0x6.w=0000`).");
+    }
+
+    faults.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEP: &str = "____________________________________________\n";
+
+    #[test]
+    fn aer_in_dmesg_and_journal_counts_once() {
+        let log = format!("{SEP}\n*** dmesg\n[  100.000000] pcieport 0000:00:01.0: AER: Corrected error received: 0000:3b:00.0\n\
+            [  100.000001] nvidia 0000:3b:00.0: PCIe Bus Error: severity=Corrected, type=Physical Layer, (Receiver ID)\n\
+            [  100.000002] nvidia 0000:3b:00.0:    [ 0] RxErr\n\
+            {SEP}\n*** journalctl -b -0 -k\nOct 14 10:00:00 node kernel: pcieport 0000:00:01.0: AER: Corrected error received: 0000:3b:00.0\n\
+            Oct 14 10:00:00 node kernel: nvidia 0000:3b:00.0: PCIe Bus Error: severity=Corrected, type=Physical Layer, (Receiver ID)\n\
+            Oct 14 10:00:00 node kernel: nvidia 0000:3b:00.0:    [ 0] RxErr\n");
+        let (devices, positions) = parse_aer(&log, &BootMap::parse(&log));
+        assert_eq!(devices["3b:00.0"].corrected, 1);
+        assert_eq!(devices["3b:00.0"].types["RxErr"], 1);
+        assert_eq!(positions.len(), 1);
+    }
+    #[test]
+    fn iommu_fault_in_dmesg_and_journal_counts_once() {
+        let fault = "DMAR: [DMA Read NO_PASID] Request device [3b:00.0] fault addr 0x1000 [fault reason 0x06] PTE Read access is not set";
+        let log = format!("{SEP}\n*** dmesg\n[  100.000000] {fault}\n{SEP}\n*** journalctl -b -0 -k\nOct 14 10:00:00 node kernel: {fault}\n");
+        let (faults, positions) = parse_iommu_faults(&log, &BootMap::parse(&log));
+        assert_eq!(faults["3b:00.0"].values().map(|(count, _)| count).sum::<usize>(), 1);
+        assert_eq!(positions.len(), 1);
+    }
+}
@@ -0,0 +1,153 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt;
+
+use crate::smi;
+
+/// NVSwitch chips show up in lspci as NVIDIA "Bridge" devices (class 0680).
+static RE_LSPCI_NVSWITCH: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:[0-9a-fA-F]{4}:)?([0-9a-fA-F]{2}:[0-9a-fA-F]{2}\.[0-7])\s+Bridge(?: \[0680\])?:\s*NVIDIA").unwrap());
+
+/// GPU models only sold as PCIe cards, whose names carry no form factor.
+static RE_PCIE_ONLY_MODEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(?:RTX|Quadro|GeForce|TITAN|T4|L4|L20|L40S?|A2|A10G?|A16|A30|A40|A[2456]000|A800 40GB Active)\b").unwrap());
+
+/// DMI product names of NVSwitch-based HGX/DGX systems that don't say so in the name.
+const HGX_PRODUCTS: &[&str] = &["DGX", "HGX", "XE9680", "XE9640", "SYS-821GE", "SYS-421GE", "AS -8125GS", "ESC N8", "G593", "XE8545"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormFactor {
+    Sxm,
+    Pcie,
+    Unknown,
+}
+
+impl fmt::Display for FormFactor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormFactor::Sxm => write!(f, "SXM"),
+            FormFactor::Pcie => write!(f, "PCIe"),
+            FormFactor::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Platform {
+    pub form_factor: FormFactor,
+    pub nvswitch_count: usize,
+    pub gpu_models: Vec<String>,
+}
+
+impl Platform {
+    /// Fabric manager is only needed, and its version conflicts only matter, on SXM systems.
+    /// Only a positively detected PCIe platform rules it out.
+    pub fn needs_fabric_manager(&self) -> bool {
+        self.form_factor != FormFactor::Pcie
+    }
+
+    /// PCIe cards only have NVLink with an optional bridge, and then nvidia-smi reports the links.
+    /// Without a known form factor, rely on the same evidence.
+    pub fn has_nvlink(&self, log: &str) -> bool {
+        self.form_factor == FormFactor::Sxm || log.contains("NVLink")
+    }
+
+    /// GPUs on the HGX baseboard, from its NVSwitch count: 8 GPUs with 2 (B200), 4 (H100/H200)
+    /// or 6 (A100) NVSwitches, 16 with 12 (HGX-2/DGX-2). Other counts are not a known layout.
+    pub fn expected_gpus(&self) -> Option<usize> {
+        match self.nvswitch_count {
+            2 | 4 | 6 => Some(8),
+            12 => Some(16),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        let mut parts = vec![self.form_factor.to_string()];
+        if self.nvswitch_count > 0 {
+            parts.push(format!("HGX with {} NVSwitch", self.nvswitch_count));
+        }
+        if !self.gpu_models.is_empty() {
+            parts.push(self.gpu_models.join(", "));
+        }
+        parts.join(", ")
+    }
+}
+
+pub fn detect(log: &str) -> Platform {
+    let system_product = crate::RE_SYS_INFO.captures(log)
+        .map_or("", |block| crate::extract_field(block.get(1).unwrap().as_str(), "Product Name:"))
+        .to_string();
+    let baseboard = crate::RE_BASEBOARD_INFO.captures(log)
+        .map_or("", |block| crate::extract_field(block.get(1).unwrap().as_str(), "Product Name:"))
+        .to_string();
+
+    let mut gpu_models: Vec<String> = smi::parse_gpus(log).iter()
+        .filter_map(|gpu| gpu.root.get(&["Product Name"]).map(str::to_string))
+        .collect();
+    gpu_models.sort();
+    gpu_models.dedup();
+
+    let mut nvswitches: Vec<&str> = RE_LSPCI_NVSWITCH.captures_iter(log).map(|cap| cap.get(1).unwrap().as_str()).collect();
+    nvswitches.sort();
+    nvswitches.dedup();
+    let nvswitch_count = nvswitches.len();
+
+    let lspci_names: Vec<&str> = crate::RE_LSPCI_NVIDIA_GPU.find_iter(log).map(|m| m.as_str()).collect();
+    let names: Vec<&str> = gpu_models.iter().map(String::as_str).chain(lspci_names).collect();
+    // nvidia-smi spells it "A100-PCIE-40GB", lspci "A100 PCIe 40GB"
+    let named = |needle: &str| names.iter().any(|name| name.to_lowercase().contains(&needle.to_lowercase()));
+    let dmi_hgx = HGX_PRODUCTS.iter().any(|p| system_product.contains(p) || baseboard.contains(p));
+
+    // SXM H100/H200 report as "NVIDIA H100 80GB HBM3" / "NVIDIA H200", without a form factor
+    let form_factor = if nvswitch_count > 0 || named("SXM") || dmi_hgx {
+        FormFactor::Sxm
+    } else if named("PCIe") || named("NVL") || names.iter().any(|name| RE_PCIE_ONLY_MODEL.is_match(name)) {
+        FormFactor::Pcie
+    } else {
+        FormFactor::Unknown
+    };
+
+    Platform { form_factor, nvswitch_count, gpu_models }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form_factor(model: &str) -> FormFactor {
+        detect(&format!("3b:00.0 3D controller [0302]: NVIDIA Corporation {} (rev a2)\n", model)).form_factor
+    }
+
+    #[test]
+    fn pcie_in_any_case() {
+        assert_eq!(form_factor("GA100 [A100 PCIe 40GB]"), FormFactor::Pcie);
+        assert_eq!(form_factor("Device [A100-PCIE-40GB]"), FormFactor::Pcie);
+        assert_eq!(form_factor("GV100GL [Tesla V100-PCIE-32GB]"), FormFactor::Pcie);
+    }
+
+    #[test]
+    fn pcie_only_models() {
+        assert_eq!(form_factor("GA102GL [RTX A6000]"), FormFactor::Pcie);
+        assert_eq!(form_factor("AD102GL [L40S]"), FormFactor::Pcie);
+        assert_eq!(form_factor("AD104GL [L4]"), FormFactor::Pcie);
+        assert_eq!(form_factor("TU104GL [Tesla T4]"), FormFactor::Pcie);
+    }
+
+    #[test]
+    fn sxm_and_unknown() {
+        assert_eq!(form_factor("GH100 [H100 SXM5 80GB]"), FormFactor::Sxm);
+        let unknown = detect("3b:00.0 3D controller [0302]: NVIDIA Corporation GA100 [A100 80GB] (rev a2)\n");
+        assert_eq!(unknown.form_factor, FormFactor::Unknown);
+        assert!(unknown.needs_fabric_manager());
+        assert!(!unknown.has_nvlink(""));
+    }
+
+    #[test]
+    fn expected_gpus_from_nvswitch_count() {
+        let platform = |nvswitch_count| Platform { form_factor: FormFactor::Sxm, nvswitch_count, gpu_models: Vec::new() };
+        assert_eq!(platform(0).expected_gpus(), None);
+        assert_eq!(platform(4).expected_gpus(), Some(8));
+        assert_eq!(platform(6).expected_gpus(), Some(8));
+        assert_eq!(platform(12).expected_gpus(), Some(16));
+        assert_eq!(platform(5).expected_gpus(), None);
+    }
+}
@@ -0,0 +1,108 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::input::{Coverage, Data};
+
+static RE_REPORT_HEADER: Lazy<Regex> = Lazy::new(|| Regex::new(r"nvidia-bug-report\.sh (?:will now collect|Version)|Start of NVIDIA bug report log file").unwrap());
+static RE_REPORT_END: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?mi)^\s*end of (?:nvidia-bug-report\.sh|NVIDIA bug report log file)").unwrap());
+static RE_FLAGS: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^command line flags:[ \t]*(.*)$").unwrap());
+static RE_SECTION_SEPARATOR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^_{20,}\s*$").unwrap());
+static RE_NON_ROOT: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r"(?mi)^.*(?:not (?:being )?run(?:ning)? as root|must be run as root|/dev/mem: Permission denied|read kernel buffer failed: Operation not permitted|not seeing messages from other users and the system).*$"
+).unwrap());
+
+/// How much of nvidia-bug-report.sh's output made it into the log. The script
+/// can hang on a wedged GPU and get killed, or run without root, and both
+/// leave sections out without saying so in the sections themselves.
+#[derive(Debug, Default)]
+pub struct DataQuality {
+    pub bug_report: bool,
+    pub complete: bool,
+    pub last_section: Option<String>,
+    pub flags: Option<String>,
+    pub non_root: Vec<String>,
+}
+
+impl DataQuality {
+    /// `report` is the bug report alone, before sections from other files are appended.
+    pub fn assess(report: &str) -> DataQuality {
+        let last_section = RE_SECTION_SEPARATOR.split(report)
+            .filter_map(|section| section.lines().map(str::trim).find(|line| !line.is_empty()))
+            .map(|title| title.trim_start_matches("*** ").to_string())
+            .last();
+        let mut non_root: Vec<String> = RE_NON_ROOT.find_iter(report).map(|m| m.as_str().trim().to_string()).collect();
+        non_root.dedup();
+
+        DataQuality {
+            bug_report: RE_REPORT_HEADER.is_match(report),
+            complete: RE_REPORT_END.is_match(report),
+            last_section,
+            flags: RE_FLAGS.captures(report).map(|cap| cap[1].trim().to_string()).filter(|flags| !flags.is_empty()),
+            non_root,
+        }
+    }
+
+    pub fn safe_mode(&self) -> bool {
+        self.flags.as_deref().is_some_and(|flags| flags.contains("--safe-mode"))
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.bug_report && !self.complete
+    }
+}
+
+fn sections(log: &str, coverage: &Coverage) -> Vec<(&'static str, bool)> {
+    vec![
+        (Data::Smi.label(), coverage.has(Data::Smi)),
+        (Data::KernelLog.label(), coverage.has(Data::KernelLog)),
+        (Data::Lspci.label(), coverage.has(Data::Lspci)),
+        ("dmidecode", log.contains("DMI type")),
+        ("/proc/driver/nvidia/version", crate::driver::kernel_module_version(log).is_some()),
+    ]
+}
+
+pub fn print_data_quality(quality: &DataQuality, log: &str, coverage: &Coverage) {
+    println!("\n==================================================");
+    println!("     Data Quality");
+    println!("==================================================\n");
+
+    if !quality.bug_report {
+        println!("Report: not an nvidia-bug-report.sh log, completeness unknown");
+    } else if quality.complete {
+        println!("Report: complete");
+    } else {
+        println!("** Report: TRUNCATED, the end of nvidia-bug-report.sh output is missing");
+        if let Some(section) = &quality.last_section {
+            println!("   Last section: {}", section);
+        }
+        println!("   The script was likely killed while hanging on a wedged GPU; sections after this point are absent.");
+    }
+
+    if quality.non_root.is_empty() {
+        if quality.bug_report {
+            println!("Collected as: root (no permission errors found)");
+        }
+    } else {
+        println!("** Collected as: non-root, some sections are missing or incomplete");
+        for line in quality.non_root.iter().take(3) {
+            println!("      {}", line);
+        }
+    }
+
+    if let Some(flags) = &quality.flags {
+        println!("Script flags: {}", flags);
+    }
+    if quality.safe_mode() {
+        println!("** Collected with --safe-mode: commands that can hang on a wedged GPU were skipped");
+    }
+
+    let sections = sections(log, coverage);
+    let present: Vec<&str> = sections.iter().filter(|(_, found)| *found).map(|(name, _)| *name).collect();
+    let missing: Vec<&str> = sections.iter().filter(|(_, found)| !*found).map(|(name, _)| *name).collect();
+    println!("Sections present: {}", if present.is_empty() { "none".to_string() } else { present.join(", ") });
+    if !missing.is_empty() {
+        println!("** Sections missing: {}", missing.join(", "));
+        println!("   Checks that depend on them are reported as not evaluated.");
+    }
+    println!();
+}
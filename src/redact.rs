@@ -0,0 +1,255 @@
+use once_cell::sync::{Lazy, OnceCell};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+static RE_UNAME_HOST: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:uname:\s*)?Linux (\S+) \d+\.\d+\S* #").unwrap());
+static RE_HOSTNAME_FIELD: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?mi)^\s*(?:static )?hostname\s*[:=]\s*(\S+)\s*$").unwrap());
+static RE_SYSLOG_HOST: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(?:\w{3}\s+\d{1,2} \d{2}:\d{2}:\d{2}|\d{4}-\d{2}-\d{2}T\S+) (\S+) \S+?(?:\[\d+\])?: ").unwrap());
+static RE_SERIAL_FIELD: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^[ \t]*[\w ]*Serial Number[ \t]*:[ \t]*(\S[^\n]*?)[ \t]*$").unwrap());
+static RE_PS_HEADER: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*USER\s+PID\b").unwrap());
+static RE_HOME_DIR: Lazy<Regex> = Lazy::new(|| Regex::new(r"/home/([A-Za-z0-9._-]+)").unwrap());
+static RE_UUID: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(GPU-|MIG-)?([0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})\b").unwrap());
+static RE_MAC: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b[0-9a-fA-F]{2}(?::[0-9a-fA-F]{2}){5,19}\b").unwrap());
+static RE_IPV4: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d+(?:\.\d+)+\b").unwrap());
+static RE_IPV6: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b[0-9a-fA-F]{1,4}(?::[0-9a-fA-F]{1,4})*::[0-9a-fA-F]{1,4}(?::[0-9a-fA-F]{1,4})*\b").unwrap());
+
+/// Values that are not identifying even though they sit in a serial number or user column.
+const PLACEHOLDERS: &[&str] = &["N/A", "Not Specified", "Not Available", "To Be Filled By O.E.M.", "Default string", "None", "Unknown", "0"];
+const SYSTEM_USERS: &[&str] = &["root", "daemon", "nobody", "syslog", "messagebus", "systemd+", "systemd-timesync", "systemd-network",
+    "systemd-resolve", "message+", "avahi", "polkitd", "rpc", "chrony", "dbus", "nvidia-persistenced", "USER"];
+
+/// Labels that make a file name look like an FQDN ("gpunode01.log.gz").
+const FILE_EXTENSIONS: &[&str] = &["log", "txt", "gz", "xz", "bz2", "tar", "tgz", "zip", "json", "csv", "out", "conf", "sh", "redacted"];
+
+/// The part of an FQDN after the host name: the last label is alphabetic and no label is a file extension.
+fn is_domain(suffix: &str) -> bool {
+    let labels: Vec<&str> = suffix.split('.').collect();
+    labels.last().is_some_and(|last| last.len() >= 2 && last.chars().all(|c| c.is_ascii_alphabetic()))
+        && !labels.iter().any(|label| FILE_EXTENSIONS.contains(&label.to_lowercase().as_str()))
+}
+
+/// A value worth redacting rather than a placeholder: long enough and not a single repeated character.
+fn is_serial(value: &str) -> bool {
+    let mut chars = value.chars();
+    let first = chars.next();
+    value.len() >= 4
+        && !chars.all(|c| Some(c) == first)
+        && !PLACEHOLDERS.iter().any(|p| p.eq_ignore_ascii_case(value))
+}
+
+/// Replaces hostnames, IP and MAC addresses, serial numbers, UUIDs and
+/// usernames with stable tokens: the same value always maps to the same token.
+/// Values are compared case-insensitively, hostnames are not case-sensitive.
+#[derive(Debug, Default)]
+pub struct Redactor {
+    tokens: HashMap<String, String>,
+    counters: HashMap<&'static str, usize>,
+    /// Every value seen so far, built on the first `redact_seen` after `redact`.
+    seen: OnceCell<Regex>,
+}
+
+impl Redactor {
+    fn token(&mut self, kind: &'static str, value: &str) -> String {
+        if let Some(token) = self.tokens.get(&value.to_lowercase()) {
+            return token.clone();
+        }
+        let counter = self.counters.entry(kind).or_default();
+        *counter += 1;
+        let token = format!("{}-{}", kind, counter);
+        self.tokens.insert(value.to_lowercase(), token.clone());
+        token
+    }
+
+    /// Number of distinct values replaced per kind.
+    pub fn counts(&self) -> Vec<(&'static str, usize)> {
+        let mut counts: Vec<_> = self.counters.iter().map(|(kind, count)| (*kind, *count)).collect();
+        counts.sort();
+        counts
+    }
+
+    /// Identifying values that appear anywhere in the log once found in a known field.
+    fn collect_literals(&mut self, log: &str) -> Vec<String> {
+        let mut found: Vec<(&'static str, String)> = Vec::new();
+        for re in [&*RE_UNAME_HOST, &*RE_HOSTNAME_FIELD, &*RE_SYSLOG_HOST] {
+            found.extend(re.captures_iter(log).map(|cap| ("HOST", cap[1].to_string())));
+        }
+
+        // First column of ps listings, plus home directories
+        for header in RE_PS_HEADER.find_iter(log) {
+            let users = log[header.end()..].lines().skip(1).take_while(|line| !line.trim().is_empty())
+                .filter_map(|line| line.split_whitespace().next());
+            found.extend(users.map(|user| ("USER", user.to_string())));
+        }
+        found.extend(RE_HOME_DIR.captures_iter(log).map(|cap| ("USER", cap[1].to_string())));
+        found.retain(|(kind, value)| *kind != "USER" || (value.len() > 2 && !SYSTEM_USERS.contains(&value.as_str())));
+
+        let mut literals: Vec<String> = Vec::new();
+        for (kind, value) in found {
+            if kind != "HOST" {
+                self.token(kind, &value);
+                literals.push(value);
+                continue;
+            }
+            // The short name and every FQDN of a host share its token; the domain gets
+            // its own so that other hosts of the same domain don't give it away
+            let short = value.split('.').next().unwrap_or(&value).to_string();
+            let token = match self.tokens.get(&short.to_lowercase()) {
+                Some(token) => token.clone(),
+                None => self.token(kind, &value),
+            };
+            let fqdn = Regex::new(&format!(r"(?i)\b{}\.([A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*)\b", regex::escape(&short))).unwrap();
+            let mut forms = vec![value.clone(), short.clone()];
+            for domain in fqdn.captures_iter(log).map(|cap| cap[1].to_string()).filter(|d| is_domain(d)) {
+                self.token("DOMAIN", &domain);
+                forms.push(format!("{}.{}", short, domain));
+                literals.push(domain);
+            }
+            for form in forms {
+                self.tokens.entry(form.to_lowercase()).or_insert_with(|| token.clone());
+                literals.push(form);
+            }
+        }
+        // Longest first so a value that contains another one is replaced whole
+        literals.sort_by_key(|l| (std::cmp::Reverse(l.len()), l.to_lowercase()));
+        literals.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        literals
+    }
+
+    /// Serial numbers are only replaced in the field they were found in: placeholder
+    /// serials such as 00000000 also appear as PCI domains in bus IDs.
+    fn redact_serials(&mut self, log: &str) -> String {
+        RE_SERIAL_FIELD.replace_all(log, |cap: &Captures| {
+            let (field, value) = (cap.get(0).unwrap(), cap.get(1).unwrap());
+            let serial = value.as_str().trim_matches('.');
+            if !is_serial(serial) {
+                return cap[0].to_string();
+            }
+            let start = value.start() - field.start();
+            format!("{}{}{}", &cap[0][..start], self.token("SERIAL", serial), &cap[0][start + value.len()..])
+        }).into_owned()
+    }
+
+    /// Replaces values `redact` has already seen in other text, such as the input file names.
+    pub fn redact_seen(&self, text: &str) -> String {
+        if self.tokens.is_empty() {
+            return text.to_string();
+        }
+        let re = self.seen.get_or_init(|| {
+            let mut values: Vec<&String> = self.tokens.keys().collect();
+            values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+            let pattern = values.iter().map(|v| regex::escape(v)).collect::<Vec<_>>().join("|");
+            Regex::new(&format!(r"(?i)\b(?:{})\b", pattern)).unwrap()
+        });
+        re.replace_all(text, |cap: &Captures| self.tokens.get(&cap[0].to_lowercase()).cloned().unwrap_or_else(|| cap[0].to_string())).into_owned()
+    }
+
+    pub fn redact(&mut self, log: &str) -> String {
+        self.seen = OnceCell::new();
+        let literals = self.collect_literals(log);
+
+        let text = self.redact_serials(log);
+        let text = RE_UUID.replace_all(&text, |cap: &Captures| {
+            format!("{}{}", cap.get(1).map_or("", |m| m.as_str()), self.token("UUID", &cap[2].to_lowercase()))
+        }).into_owned();
+        let text = RE_MAC.replace_all(&text, |cap: &Captures| self.token("MAC", &cap[0].to_lowercase())).into_owned();
+        let text = RE_IPV6.replace_all(&text, |cap: &Captures| self.token("IP", &cap[0].to_lowercase())).into_owned();
+        // Versions look like addresses too; octets with leading zeros (96.00.99.00) are not addresses
+        let mut text = RE_IPV4.replace_all(&text, |cap: &Captures| {
+            let octets: Vec<&str> = cap[0].split('.').collect();
+            let address = octets.len() == 4
+                && octets.iter().all(|o| o.parse::<u8>().is_ok() && (o.len() == 1 || !o.starts_with('0')));
+            if address { self.token("IP", &cap[0]) } else { cap[0].to_string() }
+        }).into_owned();
+
+        if !literals.is_empty() {
+            let pattern = literals.iter().map(|l| regex::escape(l)).collect::<Vec<_>>().join("|");
+            let re = Regex::new(&format!(r"(?i)\b(?:{})\b", pattern)).unwrap();
+            text = re.replace_all(&text, |cap: &Captures| self.tokens[&cap[0].to_lowercase()].clone()).into_owned();
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholder_serial_keeps_bus_ids() {
+        let log = "Memory Device\n\tSerial Number: 00000000\n\nGPU 00000000:19:00.0\n    Bus Id : 00000000:19:00.0\n";
+        let redacted = Redactor::default().redact(log);
+        assert!(redacted.contains("GPU 00000000:19:00.0"));
+        assert!(redacted.contains("Serial Number: 00000000"));
+        assert!(crate::smi::RE_GPU_SMI_BLOCK.is_match(&redacted));
+    }
+
+    #[test]
+    fn serial_replaced_only_in_its_field() {
+        let log = "System Information\n\tSerial Number: 1654321012345\n\nGPU 00000000:19:00.0\n    Serial Number : 1654321099999\n";
+        let mut redactor = Redactor::default();
+        let redacted = redactor.redact(log);
+        assert!(redacted.contains("Serial Number: SERIAL-1\n"));
+        assert!(redacted.contains("Serial Number : SERIAL-2\n"));
+        assert!(!redacted.contains("16543210"));
+        assert_eq!(redactor.counts(), vec![("SERIAL", 2)]);
+    }
+
+    #[test]
+    fn hostname_is_replaced_everywhere() {
+        let log = "uname: Linux gpu-node-07 5.15.0-119-generic #129-Ubuntu SMP\nOct 14 10:00:52 gpu-node-07 kernel: NVRM: loading\n";
+        let redacted = Redactor::default().redact(log);
+        assert!(!redacted.contains("gpu-node-07"));
+        assert_eq!(redacted.matches("HOST-1").count(), 2);
+    }
+
+    #[test]
+    fn same_value_gets_same_token() {
+        let log = "inet 10.1.2.3/24\nping 10.1.2.3\ninet 10.1.2.4/24\nlink/ether 0c:42:a1:00:11:22\n";
+        let redacted = Redactor::default().redact(log);
+        assert_eq!(redacted, "inet IP-1/24\nping IP-1\ninet IP-2/24\nlink/ether MAC-1\n");
+    }
+
+    #[test]
+    fn versions_are_not_addresses() {
+        let log = "VBIOS Version : 96.00.99.00.01\nDriver Version : 550.144.03\nkernel 5.15.0-119-generic\n";
+        assert_eq!(Redactor::default().redact(log), log);
+    }
+
+    #[test]
+    fn uuid_keeps_gpu_prefix() {
+        let log = "GPU UUID : GPU-3F2A1B4C-1111-2222-3333-444455556666\nsame: gpu-3f2a1b4c-1111-2222-3333-444455556666\n";
+        let redacted = Redactor::default().redact(log);
+        assert_eq!(redacted, "GPU UUID : GPU-UUID-1\nsame: gpu-UUID-1\n");
+    }
+
+    #[test]
+    fn file_names_use_the_same_tokens() {
+        let mut redactor = Redactor::default();
+        redactor.redact("uname: Linux gpunode01 5.15.0-119-generic #129-Ubuntu SMP\n");
+        assert_eq!(redactor.redact_seen("sos.tar.xz:sosreport-gpunode01-2026/sos_commands/kernel/dmesg"),
+            "sos.tar.xz:sosreport-HOST-1-2026/sos_commands/kernel/dmesg");
+    }
+
+    #[test]
+    fn system_users_are_kept() {
+        let log = "USER         PID %CPU\nroot           1  0.0\nalice       4242  1.0\n\nls /home/alice\n";
+        let redacted = Redactor::default().redact(log);
+        assert!(redacted.contains("root           1"));
+        assert!(!redacted.contains("alice"));
+    }
+
+    #[test]
+    fn fqdn_and_domain_are_replaced() {
+        let log = "static hostname: gpu-node-07.example.com\nping gpu-node-07\nnode02.example.com is up\n";
+        let redacted = Redactor::default().redact(log);
+        assert_eq!(redacted, "static hostname: HOST-1\nping HOST-1\nnode02.DOMAIN-1 is up\n");
+    }
+
+    #[test]
+    fn hostnames_match_in_any_case() {
+        let mut redactor = Redactor::default();
+        let redacted = redactor.redact("uname: Linux GPU-Node-07 5.15.0-119-generic #129-Ubuntu SMP\nssh gpu-node-07\n");
+        assert_eq!(redacted, "uname: Linux HOST-1 5.15.0-119-generic #129-Ubuntu SMP\nssh HOST-1\n");
+        assert_eq!(redactor.redact_seen("sosreport-GPU-NODE-07.tar.xz"), "sosreport-HOST-1.tar.xz");
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Built-in entries; a file passed with --remediation-csv adds to or overrides them.
+const BUILTIN: &str = include_str!("../remediation.csv");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Requires {
+    Nothing,
+    GpuReset,
+    NodeReboot,
+    Rma,
+}
+
+impl Requires {
+    fn parse(value: &str) -> Requires {
+        match value.trim().to_lowercase().as_str() {
+            "gpu-reset" => Requires::GpuReset,
+            "node-reboot" => Requires::NodeReboot,
+            "rma" => Requires::Rma,
+            _ => Requires::Nothing,
+        }
+    }
+}
+
+impl fmt::Display for Requires {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Requires::Nothing => write!(f, "no reset"),
+            Requires::GpuReset => write!(f, "GPU reset"),
+            Requires::NodeReboot => write!(f, "node reboot"),
+            Requires::Rma => write!(f, "RMA"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Remedy {
+    pub requires: Requires,
+    pub action: String,
+    pub link: String,
+}
+
+/// Remediation entries keyed by `xid:<code>` or `check:<summary name>`.
+#[derive(Debug, Default)]
+pub struct KnowledgeBase {
+    entries: HashMap<String, Remedy>,
+}
+
+impl KnowledgeBase {
+    pub fn load(path: Option<&Path>) -> KnowledgeBase {
+        let mut kb = KnowledgeBase::default();
+        kb.extend(csv_reader().from_reader(BUILTIN.as_bytes()));
+        if let Some(path) = path {
+            match csv_reader().from_path(path) {
+                Ok(reader) => kb.extend(reader),
+                Err(err) => eprintln!("Warning: could not read remediation CSV '{}': {}", path.display(), err),
+            }
+        }
+        kb
+    }
+
+    fn extend<R: std::io::Read>(&mut self, mut reader: csv::Reader<R>) {
+        for record in reader.records().flatten() {
+            let (Some(key), Some(action)) = (record.get(0), record.get(2)) else {
+                continue;
+            };
+            self.entries.insert(key.trim().to_lowercase(), Remedy {
+                requires: Requires::parse(record.get(1).unwrap_or("")),
+                action: action.trim().to_string(),
+                link: record.get(3).unwrap_or("").trim().to_string(),
+            });
+        }
+    }
+
+    pub fn xid(&self, code: &str) -> Option<&Remedy> {
+        self.entries.get(&format!("xid:{}", code.trim()))
+    }
+
+    pub fn check(&self, name: &str) -> Option<&Remedy> {
+        self.entries.get(&format!("check:{}", name.to_lowercase()))
+    }
+}
+
+fn csv_reader() -> csv::ReaderBuilder {
+    let mut builder = csv::ReaderBuilder::new();
+    builder.has_headers(true).comment(Some(b'#')).flexible(true);
+    builder
+}
+
+pub fn print_remedy(label: &str, remedy: &Remedy) {
+    println!("   {} [{}]: {}", label, remedy.requires, remedy.action);
+    if !remedy.link.is_empty() {
+        println!("      {}", remedy.link);
+    }
+}
@@ -0,0 +1,175 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+pub static RE_GPU_SMI_BLOCK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^GPU ([0-9A-Fa-f:\.]+)\s*$").unwrap());
+
+/// One line of `nvidia-smi -q` output. Section headers have no value and carry
+/// the more deeply indented lines below them as children.
+#[derive(Debug, Default, Clone)]
+pub struct SmiNode {
+    pub key: String,
+    pub value: Option<String>,
+    pub children: Vec<SmiNode>,
+}
+
+impl SmiNode {
+    pub fn child(&self, key: &str) -> Option<&SmiNode> {
+        self.children.iter().find(|c| c.key == key)
+    }
+
+    /// Walks `path` from this node, e.g. `&["ECC Errors", "Volatile"]`.
+    pub fn section(&self, path: &[&str]) -> Option<&SmiNode> {
+        path.iter().try_fold(self, |node, key| node.child(key))
+    }
+
+    /// Value at `path`, with "N/A" and empty values treated as missing.
+    pub fn get(&self, path: &[&str]) -> Option<&str> {
+        self.section(path)
+            .and_then(|n| n.value.as_deref())
+            .filter(|v| !v.is_empty() && *v != "N/A")
+    }
+
+    /// Leading number of the value at `path`, so "35 C", "-5 C" and "700.00 W" all parse.
+    pub fn number(&self, path: &[&str]) -> Option<f64> {
+        self.get(path)
+            .and_then(|v| v.split_whitespace().next())
+            .and_then(|v| v.parse().ok())
+    }
+
+    /// Leading integer of the value at `path`, so "0" and "2558 bank(s)" both parse.
+    pub fn count(&self, path: &[&str]) -> Option<u64> {
+        self.get(path)
+            .and_then(|v| v.split_whitespace().next())
+            .and_then(|v| v.parse().ok())
+    }
+}
+
+/// A GPU block from `nvidia-smi -q`, headed by its `GPU 00000000:19:00.0` line.
+#[derive(Debug, Clone)]
+pub struct SmiGpu {
+    pub index: usize,
+    pub bus_id: String,
+    pub root: SmiNode,
+}
+
+impl SmiGpu {
+    /// Bus ID in the short lowercase form used by lspci and the kernel, e.g. "19:00.0".
+    pub fn short_bus_id(&self) -> String {
+        short_bus_id(&self.bus_id)
+    }
+}
+
+/// Normalizes "00000000:19:00.0", "0000:19:00.0" or "19:00" to "19:00.0".
+pub fn short_bus_id(bus_id: &str) -> String {
+    let parts: Vec<&str> = bus_id.trim().split(':').collect();
+    let short = if parts.len() >= 2 {
+        format!("{}:{}", parts[parts.len() - 2], parts[parts.len() - 1])
+    } else {
+        bus_id.trim().to_string()
+    }.to_lowercase();
+    if short.contains('.') { short } else { format!("{}.0", short) }
+}
+
+/// Parses every GPU block in the log. The bug report can contain several
+/// `nvidia-smi -q` runs, so blocks for the same bus ID are merged, with the
+/// first occurrence of each section winning.
+pub fn parse_gpus(log: &str) -> Vec<SmiGpu> {
+    let lines: Vec<&str> = log.lines().collect();
+    let mut gpus: Vec<SmiGpu> = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(cap) = RE_GPU_SMI_BLOCK.captures(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        let bus_id = cap[1].to_string();
+        let start = i + 1;
+        let mut end = start;
+        while end < lines.len() && (lines[end].trim().is_empty() || lines[end].starts_with([' ', '\t'])) {
+            end += 1;
+        }
+        let entries: Vec<(usize, String, Option<String>)> = lines[start..end].iter()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| parse_line(l))
+            .collect();
+        let mut pos = 0;
+        let children = build_tree(&entries, &mut pos, 0);
+
+        if let Some(existing) = gpus.iter_mut().find(|g| g.bus_id.eq_ignore_ascii_case(&bus_id)) {
+            for child in children {
+                if existing.root.child(&child.key).is_none() {
+                    existing.root.children.push(child);
+                }
+            }
+        } else {
+            let index = gpus.len();
+            gpus.push(SmiGpu { index, bus_id, root: SmiNode { key: "GPU".to_string(), value: None, children } });
+        }
+        i = end;
+    }
+    gpus
+}
+
+fn parse_line(line: &str) -> (usize, String, Option<String>) {
+    let indent = line.len() - line.trim_start().len();
+    let line = line.trim();
+    match line.find(" :").or_else(|| line.strip_suffix(':').map(|s| s.len())) {
+        Some(idx) => (indent, line[..idx].trim().to_string(), Some(line[idx..].trim_start_matches([' ', ':']).trim().to_string())),
+        None => (indent, line.to_string(), None),
+    }
+}
+
+fn build_tree(entries: &[(usize, String, Option<String>)], pos: &mut usize, parent_indent: usize) -> Vec<SmiNode> {
+    let mut nodes = Vec::new();
+    while *pos < entries.len() && entries[*pos].0 > parent_indent {
+        let (indent, key, value) = entries[*pos].clone();
+        *pos += 1;
+        let children = build_tree(entries, pos, indent);
+        nodes.push(SmiNode { key, value, children });
+    }
+    nodes
+}
+
+/// Identity and firmware fields of one GPU, i.e. what an RMA form asks for.
+#[derive(Debug, Clone, Default)]
+pub struct GpuInventory {
+    pub index: usize,
+    pub bus_id: String,
+    pub product_name: String,
+    pub serial_number: String,
+    pub uuid: String,
+    pub board_part_number: String,
+    pub vbios_version: String,
+    pub inforom_image: String,
+    pub inforom_oem: String,
+    pub inforom_ecc: String,
+    pub inforom_power: String,
+    pub gsp_firmware: String,
+    pub memory_total: String,
+    pub module_id: String,
+}
+
+impl GpuInventory {
+    pub fn from_gpu(gpu: &SmiGpu) -> Self {
+        let field = |path: &[&str]| gpu.root.get(path).unwrap_or("N/A").to_string();
+        GpuInventory {
+            index: gpu.index,
+            bus_id: gpu.bus_id.clone(),
+            product_name: field(&["Product Name"]),
+            serial_number: field(&["Serial Number"]),
+            uuid: field(&["GPU UUID"]),
+            board_part_number: field(&["Board Part Number"]),
+            vbios_version: field(&["VBIOS Version"]),
+            inforom_image: field(&["Inforom Version", "Image Version"]),
+            inforom_oem: field(&["Inforom Version", "OEM Object"]),
+            inforom_ecc: field(&["Inforom Version", "ECC Object"]),
+            inforom_power: gpu.root.get(&["Inforom Version", "Power Management Object"])
+                .or_else(|| gpu.root.get(&["Inforom Version", "PWR Object"]))
+                .unwrap_or("N/A").to_string(),
+            gsp_firmware: field(&["GSP Firmware Version"]),
+            memory_total: field(&["FB Memory Usage", "Total"]),
+            module_id: field(&["Module ID"]),
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::boot::BootMap;
+
+static RE_DMESG_TS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\[\s*(\d+\.\d+)\]\s*(.*)$").unwrap());
+static RE_DMESG_T_TS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\[\w{3} (\w{3})\s+(\d{1,2}) (\d{2}):(\d{2}):(\d{2}) (\d{4})\]\s*(.*)$").unwrap());
+static RE_ISO_TS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(\d{4})-(\d{2})-(\d{2})[T ](\d{2}):(\d{2}):(\d{2}(?:\.\d+)?)\S*\s+\S+\s+(.*)$").unwrap());
+static RE_SYSLOG_TS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(\w{3})\s+(\d{1,2}) (\d{2}):(\d{2}):(\d{2}(?:\.\d+)?)\s+\S+\s+(.*)$").unwrap());
+static RE_REPORT_YEAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^Date:.*\b(\d{4})\b").unwrap());
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "INFO"),
+            Severity::Warning => write!(f, "WARN"),
+            Severity::Critical => write!(f, "CRIT"),
+        }
+    }
+}
+
+/// Event signatures for the timeline, mirroring what the individual checks look for.
+static SIGNATURES: Lazy<Vec<(&'static str, Severity, Regex)>> = Lazy::new(|| {
+    [
+        ("Boot", Severity::Info, r"Linux version \d"),
+        ("Xid", Severity::Critical, r"NVRM: Xid \(PCI:"),
+        ("Fallen off bus", Severity::Critical, r"GPU has fallen off the bus"),
+        ("RmInit failure", Severity::Critical, r"RmInitAdapter failed|rm_init_adapter failed"),
+        ("PCIe AER", Severity::Critical, r"AER: (?:Multiple )?Uncorrected|PCIe Bus Error: severity=Uncorrected"),
+        ("PCIe AER", Severity::Warning, r"AER: (?:Multiple )?Corrected error"),
+        ("IOMMU fault", Severity::Warning, r"DMAR: ?\[(?:DMA|INTR)|AMD-Vi: Event logged \[IO_PAGE_FAULT"),
+        ("EDAC", Severity::Critical, r"EDAC (?:\w+ )?MC\d+: \d+ UE"),
+        ("EDAC", Severity::Warning, r"EDAC (?:\w+ )?MC\d+: \d+ CE"),
+        ("MCE", Severity::Warning, r"Machine Check(?: Exception)?: [0-9a-fx]+ Bank"),
+        ("Driver mismatch", Severity::Critical, r"NVRM: API mismatch|Please update with matching NVIDIA driver"),
+        ("Power state", Severity::Warning, r"Refused to change power state"),
+        ("Segfault", Severity::Warning, r"segfault at"),
+        ("CPU throttle", Severity::Warning, r"cpu clock throttled"),
+        ("Bad CPU", Severity::Warning, r"bad cpu"),
+        ("Kernel panic", Severity::Critical, r"Kernel panic"),
+    ]
+    .into_iter()
+    .map(|(category, severity, pattern)| (category, severity, Regex::new(pattern).unwrap()))
+    .collect()
+});
+
+/// Wall-clock time as printed in the log; syslog lines carry no year, so the
+/// year of the report's Date: header, or else the current year, is assumed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallClock {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: f64,
+}
+
+impl WallClock {
+    /// Seconds since 1970-01-01, for ordering and windowing.
+    pub fn epoch_seconds(&self) -> f64 {
+        // Howard Hinnant's days_from_civil
+        let (y, m) = if self.month <= 2 { (self.year - 1, self.month + 9) } else { (self.year, self.month - 3) };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * m as i64 + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+        (days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60) as f64 + self.second
+    }
+
+    pub fn from_epoch_seconds(seconds: f64) -> WallClock {
+        // Howard Hinnant's civil_from_days
+        let days = (seconds / 86400.0).floor() as i64;
+        let second_of_day = seconds - days as f64 * 86400.0;
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        WallClock {
+            year,
+            month,
+            day,
+            hour: (second_of_day / 3600.0) as u32,
+            minute: (second_of_day % 3600.0 / 60.0) as u32,
+            second: second_of_day % 60.0,
+        }
+    }
+}
+
+impl fmt::Display for WallClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute, self.second as u32)
+    }
+}
+
+/// dmesg timestamps count seconds since boot; they are placed on the wall clock
+/// when the journal shows when that boot started, and ordered separately otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timestamp {
+    WallClock(WallClock),
+    SinceBoot(f64),
+}
+
+impl Timestamp {
+    pub fn seconds(&self) -> f64 {
+        match self {
+            Timestamp::WallClock(w) => w.epoch_seconds(),
+            Timestamp::SinceBoot(s) => *s,
+        }
+    }
+
+    pub fn same_domain(&self, other: &Timestamp) -> bool {
+        matches!((self, other), (Timestamp::WallClock(_), Timestamp::WallClock(_)) | (Timestamp::SinceBoot(_), Timestamp::SinceBoot(_)))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timestamp::WallClock(w) => write!(f, "{}", w),
+            Timestamp::SinceBoot(s) => write!(f, "[{:>12.6}]", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub line: usize,
+    pub time: Timestamp,
+    pub category: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    MONTHS.iter().position(|m| *m == name).map(|i| i as u32 + 1)
+}
+
+/// Splits a log line into its timestamp and the message after it.
+pub fn parse_timestamp(line: &str, default_year: i64) -> Option<(Timestamp, &str)> {
+    if let Some(cap) = RE_DMESG_TS.captures(line) {
+        return Some((Timestamp::SinceBoot(cap[1].parse().ok()?), cap.get(2).unwrap().as_str()));
+    }
+    if let Some(cap) = RE_DMESG_T_TS.captures(line) {
+        let time = WallClock {
+            year: cap[6].parse().ok()?,
+            month: month_number(&cap[1])?,
+            day: cap[2].parse().ok()?,
+            hour: cap[3].parse().ok()?,
+            minute: cap[4].parse().ok()?,
+            second: cap[5].parse().ok()?,
+        };
+        return Some((Timestamp::WallClock(time), cap.get(7).unwrap().as_str()));
+    }
+    if let Some(cap) = RE_ISO_TS.captures(line) {
+        let time = WallClock {
+            year: cap[1].parse().ok()?,
+            month: cap[2].parse().ok()?,
+            day: cap[3].parse().ok()?,
+            hour: cap[4].parse().ok()?,
+            minute: cap[5].parse().ok()?,
+            second: cap[6].parse().ok()?,
+        };
+        return Some((Timestamp::WallClock(time), cap.get(7).unwrap().as_str()));
+    }
+    if let Some(cap) = RE_SYSLOG_TS.captures(line) {
+        let time = WallClock {
+            year: default_year,
+            month: month_number(&cap[1])?,
+            day: cap[2].parse().ok()?,
+            hour: cap[3].parse().ok()?,
+            minute: cap[4].parse().ok()?,
+            second: cap[5].parse().ok()?,
+        };
+        return Some((Timestamp::WallClock(time), cap.get(6).unwrap().as_str()));
+    }
+    None
+}
+
+/// The kernel message of a dmesg, journal or syslog line, without its timestamp and "host kernel: " prefix.
+pub fn kernel_message(line: &str) -> &str {
+    let message = parse_timestamp(line, 0).map_or(line, |(_, message)| message);
+    message.split_once("kernel: ").map_or(message, |(_, m)| m).trim()
+}
+
+pub fn report_year(log: &str) -> i64 {
+    RE_REPORT_YEAR.captures(log).and_then(|cap| cap[1].parse().ok()).unwrap_or_else(|| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64());
+        WallClock::from_epoch_seconds(now).year
+    })
+}
+
+/// Wall-clock start of each boot (by journalctl -b offset), from the journal's
+/// kernel banner, or else from a kernel message logged by both dmesg and the journal.
+fn boot_anchors(timed: &[(i32, Timestamp, &str)]) -> HashMap<i32, f64> {
+    let mut anchors = HashMap::new();
+    for (offset, time, message) in timed {
+        if let Timestamp::WallClock(wall) = time {
+            if message.contains("Linux version") {
+                anchors.entry(*offset).or_insert(wall.epoch_seconds());
+            }
+        }
+    }
+    for (offset, time, message) in timed {
+        let Timestamp::SinceBoot(since_boot) = time else { continue };
+        if anchors.contains_key(offset) {
+            continue;
+        }
+        let same = timed.iter().find(|(o, t, m)| o == offset && m == message && matches!(t, Timestamp::WallClock(_)));
+        if let Some((_, wall, _)) = same {
+            anchors.insert(*offset, wall.seconds() - since_boot);
+        }
+    }
+    anchors
+}
+
+/// Every timestamped line that matches one of the check signatures, in log order.
+/// dmesg events of a boot with a known start are moved onto the wall clock, and
+/// dropped when the journal already has the same message at that time.
+pub fn collect_events(log: &str, boots: &BootMap) -> Vec<Event> {
+    let year = report_year(log);
+    let mut pos = 0;
+    let mut timed = Vec::new();
+    for (line, text) in log.split_inclusive('\n').enumerate() {
+        let start = pos;
+        pos += text.len();
+        let text = text.trim_end();
+        let Some((time, _)) = parse_timestamp(text, year) else { continue };
+        let message = kernel_message(text).to_string();
+        let signature = SIGNATURES.iter().find(|(_, _, re)| re.is_match(text)).map(|(c, s, _)| (*c, *s));
+        timed.push((boots.offset_at(start), time, message, line, signature));
+    }
+
+    let anchors = boot_anchors(&timed.iter().map(|(o, t, m, _, _)| (*o, *t, m.as_str())).collect::<Vec<_>>());
+    let mut events: Vec<Event> = Vec::new();
+    for (offset, time, message, line, signature) in timed {
+        let Some((category, severity)) = signature else { continue };
+        let time = match (time, anchors.get(&offset)) {
+            (Timestamp::SinceBoot(since_boot), Some(start)) => Timestamp::WallClock(WallClock::from_epoch_seconds(start + since_boot)),
+            _ => time,
+        };
+        events.push(Event { line, time, category, severity, message });
+    }
+
+    // The same kernel message from dmesg and the journal is one event. Kept events are
+    // indexed by message, clock domain and 2 second bucket; a match within 2s is in a neighbouring bucket.
+    let mut seen: HashMap<(String, bool, i64), Vec<f64>> = HashMap::new();
+    let mut unique: Vec<Event> = Vec::new();
+    for event in events {
+        let wall_clock = matches!(event.time, Timestamp::WallClock(_));
+        let seconds = event.time.seconds();
+        let bucket = (seconds / 2.0).floor() as i64;
+        let duplicate = (bucket - 1..=bucket + 1).any(|b| {
+            seen.get(&(event.message.clone(), wall_clock, b)).is_some_and(|times| times.iter().any(|t| (t - seconds).abs() <= 2.0))
+        });
+        if !duplicate {
+            seen.entry((event.message.clone(), wall_clock, bucket)).or_default().push(seconds);
+            unique.push(event);
+        }
+    }
+    unique
+}
+
+pub fn print_timeline(log: &str, boots: &BootMap, window: Option<f64>) {
+    println!("\n==================================================");
+    println!("     Event Timeline");
+    println!("==================================================\n");
+
+    let events = collect_events(log, boots);
+    if events.is_empty() {
+        println!("No timestamped events found");
+        return;
+    }
+
+    for (title, wall_clock) in [("Wall clock (journal/syslog, dmesg placed by boot time)", true), ("Seconds since boot (dmesg, boot time unknown)", false)] {
+        let mut domain: Vec<&Event> = events.iter()
+            .filter(|e| matches!(e.time, Timestamp::WallClock(_)) == wall_clock)
+            .collect();
+        if domain.is_empty() {
+            continue;
+        }
+        domain.sort_by(|a, b| {
+            a.time.seconds().partial_cmp(&b.time.seconds()).unwrap_or(Ordering::Equal).then(a.line.cmp(&b.line))
+        });
+
+        if let Some(window) = window {
+            let Some(first) = domain.iter().find(|e| e.severity == Severity::Critical).map(|e| e.time) else {
+                println!("{}: no critical events to center the window on", title);
+                continue;
+            };
+            domain.retain(|e| e.time.same_domain(&first) && (e.time.seconds() - first.seconds()).abs() <= window);
+            println!("{} (±{}s around the first critical event at {}):", title, window, first);
+        } else {
+            println!("{}:", title);
+        }
+
+        for event in domain {
+            println!("   {}  {}  {:<16} {}", event.time, event.severity, event.category, event.message);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch_round_trip() {
+        let time = WallClock { year: 2024, month: 2, day: 29, hour: 23, minute: 59, second: 30.0 };
+        assert_eq!(WallClock::from_epoch_seconds(time.epoch_seconds()), time);
+    }
+
+    #[test]
+    fn dmesg_placed_on_journal_clock() {
+        let log = "Date: Tue Oct 14 10:00:52 AM UTC 2025\n\
+            Oct 14 09:00:00 node kernel: Linux version 5.15.0-119-generic #129-Ubuntu SMP\n\
+            Oct 14 09:10:00 node kernel: NVRM: Xid (PCI:0000:3b:00): 79, pid=1, GPU has fallen off the bus.\n\
+            ____________________________________________\n\
+            [    0.000000] Linux version 5.15.0-119-generic #129-Ubuntu SMP\n\
+            [  600.000000] NVRM: Xid (PCI:0000:3b:00): 79, pid=1, GPU has fallen off the bus.\n\
+            [  700.000000] pcieport 0000:37:01.0: AER: Corrected error received: 0000:37:01.0\n";
+        let events = collect_events(log, &BootMap::parse(log));
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|e| matches!(e.time, Timestamp::WallClock(_))));
+        assert_eq!(events[2].time.to_string(), "2025-10-14 09:11:40");
+    }
+}